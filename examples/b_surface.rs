@@ -29,8 +29,10 @@ fn main() {
 
     let t = Triangulation::new(0.1, &surface);
 
-    let mut data = ObjData::default();
-    data.position = Vec::from_iter(t.points().iter().map(|p| [p.x, p.y, p.z]));
+    let mut data = ObjData {
+        position: Vec::from_iter(t.points().iter().map(|p| [p.x, p.y, p.z])),
+        ..Default::default()
+    };
     data.objects.push(Object {
         name: "surface".to_string(),
         groups: vec![Group {
@@ -1,23 +1,24 @@
+use crate::bezier::{decompose, Linear};
 use crate::grid::Grid;
 use crate::knots::Knots;
 use crate::surfaces::UV;
-use crate::types::{Scalar, Vector};
+use crate::types::{Real, Scalar, Vector};
 use alloc::vec::Vec;
 use nalgebra::allocator::Allocator;
-use nalgebra::{DefaultAllocator, Dim};
+use nalgebra::{DMatrix, DefaultAllocator, Dim, DimDiff, DimSub, U1};
 
 pub fn cox_de_boor_u<D: Dim, T: Scalar>(
     u: T,
     degree: usize,
-    knots: &Knots,
+    knots: &Knots<T>,
     control_points: impl Fn(usize) -> Vector<D, T>,
 ) -> Vector<D, T>
 where
     DefaultAllocator: Allocator<T, D>,
 {
-    assert!(knots.range().contains(&u.cast()), "u out of range");
+    assert!(knots.range().contains(&u), "u out of range");
 
-    let k = knots.find_span(u.cast());
+    let k = knots.find_span(u);
     let mut d = Vec::with_capacity(degree + 1);
     for j in 0..degree + 1 {
         let i = j + k - degree;
@@ -32,18 +33,18 @@ where
 pub fn cox_de_boor_uv<D: Dim, T: Scalar>(
     (u, v): UV<T>,
     degree: usize,
-    u_knots: &Knots,
-    v_knots: &Knots,
+    u_knots: &Knots<T>,
+    v_knots: &Knots<T>,
     control_points: impl Fn(UV<usize>) -> Vector<D, T>,
 ) -> Vector<D, T>
 where
     DefaultAllocator: Allocator<T, D>,
 {
-    assert!(u_knots.range().contains(&u.cast()), "u out of range");
-    assert!(v_knots.range().contains(&v.cast()), "v out of range");
+    assert!(u_knots.range().contains(&u), "u out of range");
+    assert!(v_knots.range().contains(&v), "v out of range");
 
-    let u_k = u_knots.find_span(u.cast());
-    let v_k = v_knots.find_span(v.cast());
+    let u_k = u_knots.find_span(u);
+    let v_k = v_knots.find_span(v);
 
     let mut d = Grid::with_capacity(degree + 1, degree + 1);
     for v_j in 0..degree + 1 {
@@ -68,11 +69,362 @@ where
     d[(degree, degree)].clone()
 }
 
+/// Insert the knot `u` once into `span`, where `knots[span] <= u <= knots[span + 1]`.
+pub fn insert_knot<T: Scalar, P: Linear<T>>(
+    degree: usize,
+    knots: &mut Vec<T>,
+    points: &mut Vec<P>,
+    span: usize,
+    u: T,
+) {
+    if degree == 0 {
+        points.insert(span + 1, points[span].clone());
+    } else {
+        let mut inserted = Vec::with_capacity(degree);
+        for i in span + 1 - degree..=span {
+            let alpha = (u - knots[i]) / (knots[i + degree] - knots[i]);
+            inserted.push(points[i - 1].clone() * (T::one() - alpha) + points[i].clone() * alpha);
+        }
+        points.splice(span + 1 - degree..span, inserted);
+    }
+    knots.insert(span + 1, u);
+}
+
+/// Remove one copy of the knot at `r`, which must be its last copy and removable without
+/// changing the curve.
+pub fn remove_knot<T: Scalar, P: Linear<T>>(
+    degree: usize,
+    knots: &mut Vec<T>,
+    points: &mut Vec<P>,
+    r: usize,
+) {
+    let u = knots[r];
+    let multiplicity = knots.iter().filter(|&&k| k == u).count();
+    let (first, last) = (r - degree, r - multiplicity);
+
+    // solve for the new points from both ends towards the middle
+    let off = first - 1;
+    let mut temp = alloc::vec![points[off].clone(); last + 2 - off];
+    temp[last + 1 - off] = points[last + 1].clone();
+    let (mut i, mut j) = (first, last);
+    while j > i {
+        let alpha_i = (u - knots[i]) / (knots[i + degree + 1] - knots[i]);
+        let alpha_j = (u - knots[j]) / (knots[j + degree + 1] - knots[j]);
+        temp[i - off] = (points[i].clone() - temp[i - off - 1].clone() * (T::one() - alpha_i))
+            * (T::one() / alpha_i);
+        temp[j - off] = (points[j].clone() - temp[j - off + 1].clone() * alpha_j)
+            * (T::one() / (T::one() - alpha_j));
+        i += 1;
+        j -= 1;
+    }
+
+    let (mut i, mut j) = (first, last);
+    while j > i {
+        points[i] = temp[i - off].clone();
+        points[j] = temp[j - off].clone();
+        i += 1;
+        j -= 1;
+    }
+    knots.remove(r);
+    points.remove((2 * r - multiplicity - degree) / 2);
+}
+
+/// Knots and points of the clamped spline one degree higher covering the range of `knots`.
+pub fn elevate<T: Scalar, P: Linear<T>>(
+    degree: usize,
+    knots: Vec<T>,
+    points: Vec<P>,
+) -> (Vec<T>, Vec<P>) {
+    let segments = decompose(degree, knots.clone(), points);
+
+    // join the elevated segments which only meet with positional continuity
+    let mut elevated_knots = alloc::vec![segments[0].start; degree + 2];
+    let mut elevated_points = Vec::new();
+    for (i, segment) in segments.iter().enumerate() {
+        let elevated = segment.elevate();
+        let skip = if i == 0 { 0 } else { 1 };
+        elevated_points.extend(elevated.points.into_iter().skip(skip));
+        let copies = if i == segments.len() - 1 {
+            degree + 2
+        } else {
+            degree + 1
+        };
+        elevated_knots.extend(core::iter::repeat_n(segment.end, copies));
+    }
+
+    // the elevated curve is as continuous as the original, so the knots can be
+    // removed until they have one more copy than they had
+    for segment in &segments[..segments.len() - 1] {
+        let u = segment.end;
+        let multiplicity = knots.iter().filter(|&&k| k == u).count().min(degree);
+        for _ in multiplicity..degree {
+            let r = elevated_knots.iter().rposition(|&k| k == u).unwrap();
+            remove_knot(degree + 1, &mut elevated_knots, &mut elevated_points, r);
+        }
+    }
+    (elevated_knots, elevated_points)
+}
+
+/// Knots and points of the clamped spline covering `start..=end`, which lies within the range of `knots`.
+pub fn trim<T: Scalar, P: Linear<T>>(
+    degree: usize,
+    mut knots: Vec<T>,
+    mut points: Vec<P>,
+    (start, end): (T, T),
+) -> (Vec<T>, Vec<P>) {
+    // the start is inserted into the span to its right and the end into the span to
+    // its left, so that only points in range are used
+    for _ in knots.iter().filter(|&&k| k == start).count()..degree {
+        let span = knots.iter().rposition(|&k| k <= start).unwrap();
+        insert_knot(degree, &mut knots, &mut points, span, start);
+    }
+    for _ in knots.iter().filter(|&&k| k == end).count()..degree {
+        let span = knots.iter().rposition(|&k| k < end).unwrap();
+        insert_knot(degree, &mut knots, &mut points, span, end);
+    }
+
+    // both ends now have `degree` knots next to the point which the curve passes through
+    let e = knots.iter().position(|&k| k == end).unwrap();
+    knots.truncate(e + degree);
+    knots.push(end);
+    points.truncate(e);
+
+    let s = knots.iter().rposition(|&k| k == start).unwrap() + 1 - degree;
+    knots.drain(..s);
+    knots.insert(0, start);
+    points.drain(..s - 1);
+
+    (knots, points)
+}
+
+/// Control point with its position multiplied by its weight, the last coordinate.
+pub fn weight<D: Dim, T: Scalar>(point: &Vector<D, T>) -> Vector<D, T>
+where
+    DefaultAllocator: Allocator<T, D>,
+{
+    let mut weighted = point.clone();
+    let w = point[point.len() - 1];
+    for i in 0..point.len() - 1 {
+        weighted[i] *= w;
+    }
+    weighted
+}
+
+/// Inverse of [`weight`].
+pub fn unweight<D: Dim, T: Scalar>(point: &Vector<D, T>) -> Vector<D, T>
+where
+    DefaultAllocator: Allocator<T, D>,
+{
+    let mut unweighted = point.clone();
+    let w = point[point.len() - 1];
+    for i in 0..point.len() - 1 {
+        unweighted[i] /= w;
+    }
+    unweighted
+}
+
+/// Derivative of a projected homogeneous point from the point and its derivative.
+pub fn project_derivative<D: DimSub<U1>, T: Scalar>(
+    point: &Vector<D, T>,
+    derivative: &Vector<D, T>,
+) -> Vector<DimDiff<D, U1>, T>
+where
+    DefaultAllocator: Allocator<T, D> + Allocator<T, DimDiff<D, U1>>,
+    <DefaultAllocator as Allocator<T, DimDiff<D, U1>>>::Buffer: Default,
+{
+    let mut projected = Vector::<DimDiff<D, U1>, T>::default();
+    let w = point[point.len() - 1];
+    let dw = derivative[derivative.len() - 1];
+    for i in 0..projected.len() {
+        projected[i] = (derivative[i] - point[i] / w * dw) / w;
+    }
+    projected
+}
+
+/// Values of the `degree + 1` basis functions which are non zero at `u` within `span`,
+/// those of the control points from `span - degree` to `span`.
+pub fn basis_functions<T: Scalar>(u: T, span: usize, degree: usize, knots: &Knots<T>) -> Vec<T> {
+    let zero = T::cast_from(0);
+    let mut values = alloc::vec![zero; degree + 1];
+    let mut left = alloc::vec![zero; degree + 1];
+    let mut right = alloc::vec![zero; degree + 1];
+    values[0] = T::one();
+    for j in 1..=degree {
+        left[j] = u - knots[span + 1 - j];
+        right[j] = knots[span + j] - u;
+        let mut saved = zero;
+        for r in 0..j {
+            let temp = values[r] / (right[r + 1] + left[j - r]);
+            values[r] = saved + right[r + 1] * temp;
+            saved = left[j - r] * temp;
+        }
+        values[j] = saved;
+    }
+    values
+}
+
+/// First derivatives of the `degree + 1` basis functions which are non zero at `u`
+/// within `span`, those of the control points from `span - degree` to `span`.
+pub fn basis_derivatives<T: Scalar>(u: T, span: usize, degree: usize, knots: &Knots<T>) -> Vec<T> {
+    if degree == 0 {
+        return alloc::vec![T::cast_from(0)];
+    }
+    raise_derivative(
+        basis_functions(u, span, degree - 1, knots),
+        span,
+        degree,
+        knots,
+    )
+}
+
+/// Second derivatives of the `degree + 1` basis functions which are non zero at `u`
+/// within `span`, those of the control points from `span - degree` to `span`.
+pub fn basis_second_derivatives<T: Scalar>(
+    u: T,
+    span: usize,
+    degree: usize,
+    knots: &Knots<T>,
+) -> Vec<T> {
+    if degree == 0 {
+        return alloc::vec![T::cast_from(0)];
+    }
+    raise_derivative(
+        basis_derivatives(u, span, degree - 1, knots),
+        span,
+        degree,
+        knots,
+    )
+}
+
+/// Derivatives of the basis functions of `degree` from one order lower derivatives of
+/// those of `degree - 1`.
+fn raise_derivative<T: Scalar>(
+    lower: Vec<T>,
+    span: usize,
+    degree: usize,
+    knots: &Knots<T>,
+) -> Vec<T> {
+    let zero = T::cast_from(0);
+    let ratio = |value: T, start: usize, end: usize| {
+        let width = knots[end] - knots[start];
+        if width == zero {
+            zero
+        } else {
+            value / width
+        }
+    };
+    (0..=degree)
+        .map(|i| {
+            let index = span - degree + i;
+            let left = if i > 0 {
+                ratio(lower[i - 1], index, index + degree)
+            } else {
+                zero
+            };
+            let right = if i < degree {
+                ratio(lower[i], index + 1, index + degree + 1)
+            } else {
+                zero
+            };
+            (left - right) * T::cast_from(degree)
+        })
+        .collect()
+}
+
+/// Clamped knots where each interior knot is the average of `degree` consecutive parameters.
+pub fn averaged_knots<T: Real>(parameters: &[T], degree: usize) -> Vec<T> {
+    let n = parameters.len();
+    let mut knots = alloc::vec![parameters[0]; degree + 1];
+    for j in 1..n - degree {
+        let sum = parameters[j..j + degree]
+            .iter()
+            .fold(T::zero(), |a, &b| a + b);
+        knots.push(sum / T::cast_from(degree));
+    }
+    knots.extend(core::iter::repeat_n(parameters[n - 1], degree + 1));
+    knots
+}
+
+/// Clamped knots from `start` to `end` with evenly spaced interior knots for `count`
+/// control points.
+pub fn uniform_knots<T: Real>(degree: usize, count: usize, start: T, end: T) -> Vec<T> {
+    let spans = count - degree;
+    let mut knots = alloc::vec![start; degree];
+    knots
+        .extend((0..=spans).map(|i| start + (end - start) * T::cast_from(i) / T::cast_from(spans)));
+    knots.extend(core::iter::repeat_n(end, degree));
+    knots
+}
+
+/// Values of the basis functions of a clamped spline with `knots` at each of the
+/// `parameters`, one row per parameter and one column per control point.
+pub fn collocation<T: Real>(parameters: &[T], knots: &[T], degree: usize) -> DMatrix<T> {
+    let k = Knots::new(degree, knots);
+    let mut matrix = DMatrix::zeros(parameters.len(), knots.len() - degree - 1);
+    for (row, &u) in parameters.iter().enumerate() {
+        let span = k.find_span(u);
+        for (i, value) in basis_functions(u, span, degree, &k).into_iter().enumerate() {
+            matrix[(row, span - degree + i)] = value;
+        }
+    }
+    matrix
+}
+
+/// Solve for the control points of a spline from the values of the basis functions at
+/// the parameters of `points`.
+pub fn solve<D: Dim, T: Real>(
+    matrix: DMatrix<T>,
+    points: &[Vector<D, T>],
+) -> Option<Vec<Vector<D, T>>>
+where
+    DefaultAllocator: Allocator<T, D>,
+{
+    let dimensions = points[0].len();
+    let values = DMatrix::from_fn(points.len(), dimensions, |r, c| points[r][c]);
+    let solution = solve_matrix(matrix, values)?;
+    Some(
+        (0..solution.nrows())
+            .map(|r| {
+                let mut point = points[0].clone();
+                for c in 0..dimensions {
+                    point[c] = solution[(r, c)];
+                }
+                point
+            })
+            .collect(),
+    )
+}
+
+/// Solution of the square system `matrix * x = values`, if it has a finite one.
+fn solve_matrix<T: Real>(matrix: DMatrix<T>, values: DMatrix<T>) -> Option<DMatrix<T>> {
+    let solution = matrix.lu().solve(&values)?;
+    solution.iter().all(|x| x.is_finite()).then_some(solution)
+}
+
+/// Distance from `point` to the closest point of the segment from `a` to `b`.
+pub fn distance_to_segment<D: Dim, T: Real>(
+    point: &Vector<D, T>,
+    a: &Vector<D, T>,
+    b: &Vector<D, T>,
+) -> T
+where
+    DefaultAllocator: Allocator<T, D>,
+{
+    let ab = b - a;
+    let length = ab.norm_squared();
+    let t = if length > T::zero() {
+        ((point - a).dot(&ab) / length).clamp(T::zero(), T::cast_from(1))
+    } else {
+        T::zero()
+    };
+    (a + ab * t - point).norm()
+}
+
 fn cox_de_boor<D: Dim, T: Scalar>(
     u: T,
     degree: usize,
     k: usize,
-    knots: &Knots,
+    knots: &Knots<T>,
     d: &mut [Vector<D, T>],
 ) where
     DefaultAllocator: Allocator<T, D>,
@@ -85,9 +437,16 @@ fn cox_de_boor<D: Dim, T: Scalar>(
     }
 }
 
-fn alpha<T: Scalar>(u: T, knot_span: usize, degree: usize, r: usize, j: usize, knots: &Knots) -> T {
-    let kp = T::cast_from(knots[j + knot_span - degree]);
-    let kp_1 = T::cast_from(knots[j + 1 + knot_span - r]);
+fn alpha<T: Scalar>(
+    u: T,
+    knot_span: usize,
+    degree: usize,
+    r: usize,
+    j: usize,
+    knots: &Knots<T>,
+) -> T {
+    let kp = knots[j + knot_span - degree];
+    let kp_1 = knots[j + 1 + knot_span - r];
     assert_ne!(kp, kp_1, "divide by zero");
     (u - kp) / (kp_1 - kp)
 }
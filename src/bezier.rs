@@ -0,0 +1,470 @@
+use crate::algorithms::insert_knot;
+use crate::grid::Grid;
+use crate::surfaces::UV;
+use crate::types::{Real, Scalar};
+use alloc::vec::Vec;
+use core::ops::{Add, Mul, Sub};
+
+/// Values that can be linearly combined such as points, vectors and scalars.
+pub trait Linear<T>:
+    Clone + Add<Output = Self> + Sub<Output = Self> + Mul<T, Output = Self>
+{
+}
+
+impl<T, P> Linear<T> for P where P: Clone + Add<Output = P> + Sub<Output = P> + Mul<T, Output = P> {}
+
+/// A single polynomial piece of a spline in Bezier form covering `start..=end`.
+#[derive(Debug, Clone)]
+pub struct Bezier<T, P> {
+    pub start: T,
+    pub end: T,
+    pub points: Vec<P>,
+}
+
+impl<T: Scalar, P: Linear<T>> Bezier<T, P> {
+    /// Degree of the segment.
+    pub fn degree(&self) -> usize {
+        self.points.len() - 1
+    }
+
+    /// Spline parameter at local position `t` in `0..=1`.
+    pub fn parameter(&self, t: T) -> T {
+        self.start + (self.end - self.start) * t
+    }
+
+    /// Point at local position `t` in `0..=1`.
+    pub fn at(&self, t: T) -> P {
+        let mut d = self.points.clone();
+        for r in 1..d.len() {
+            for j in 0..d.len() - r {
+                d[j] = lerp(&d[j], &d[j + 1], t);
+            }
+        }
+        d.swap_remove(0)
+    }
+
+    /// Split the segment at local position `t` in `0..=1`.
+    pub fn split(&self, t: T) -> (Self, Self) {
+        let mut d = self.points.clone();
+        let mut left = Vec::with_capacity(d.len());
+        let mut right = Vec::with_capacity(d.len());
+        left.push(d[0].clone());
+        right.push(d[d.len() - 1].clone());
+        for r in 1..d.len() {
+            for j in 0..d.len() - r {
+                d[j] = lerp(&d[j], &d[j + 1], t);
+            }
+            left.push(d[0].clone());
+            right.push(d[d.len() - r - 1].clone());
+        }
+        right.reverse();
+
+        let middle = self.parameter(t);
+        (
+            Self {
+                start: self.start,
+                end: middle,
+                points: left,
+            },
+            Self {
+                start: middle,
+                end: self.end,
+                points: right,
+            },
+        )
+    }
+
+    /// Derivative with respect to the spline parameter.
+    pub fn derivative(&self) -> Self {
+        let scale = T::cast_from(self.degree()) / (self.end - self.start);
+        let points = if self.degree() == 0 {
+            Vec::from([self.points[0].clone() * T::default()])
+        } else {
+            self.points
+                .windows(2)
+                .map(|w| (w[1].clone() - w[0].clone()) * scale)
+                .collect()
+        };
+
+        Self {
+            start: self.start,
+            end: self.end,
+            points,
+        }
+    }
+
+    /// The same segment represented with one degree higher.
+    pub fn elevate(&self) -> Self {
+        let n = self.points.len();
+        let mut points = Vec::with_capacity(n + 1);
+        points.push(self.points[0].clone());
+        for i in 1..n {
+            let a = T::cast_from(i) / T::cast_from(n);
+            points.push(lerp(&self.points[i], &self.points[i - 1], a));
+        }
+        points.push(self.points[n - 1].clone());
+
+        Self {
+            start: self.start,
+            end: self.end,
+            points,
+        }
+    }
+}
+
+/// A single polynomial piece of a surface in tensor product Bezier form covering `u` by `v`.
+#[derive(Debug, Clone)]
+pub struct BezierPatch<T, P> {
+    pub u: (T, T),
+    pub v: (T, T),
+    pub points: Grid<P>,
+}
+
+impl<T: Scalar, P: Linear<T>> BezierPatch<T, P> {
+    /// Degree in the u and v directions.
+    pub fn degree(&self) -> UV<usize> {
+        (self.points.len() - 1, self.points.height() - 1)
+    }
+
+    /// Local position in `0..=1` of the surface coordinate `uv`.
+    pub fn local(&self, (u, v): UV<T>) -> UV<T> {
+        (
+            (u - self.u.0) / (self.u.1 - self.u.0),
+            (v - self.v.0) / (self.v.1 - self.v.0),
+        )
+    }
+
+    /// Surface coordinate of the local position `st` in `0..=1`.
+    pub fn parameter(&self, (s, t): UV<T>) -> UV<T> {
+        (
+            self.u.0 + (self.u.1 - self.u.0) * s,
+            self.v.0 + (self.v.1 - self.v.0) * t,
+        )
+    }
+
+    /// Point and its partial derivatives in u and v at the local position `st`.
+    pub fn derivatives(&self, (s, t): UV<T>) -> (P, P, P) {
+        let (p, q) = self.degree();
+        let (bu, dbu) = bernstein(p, s);
+        let (bv, dbv) = bernstein(q, t);
+        let zero = self.points[(0, 0)].clone() * T::default();
+
+        let (mut point, mut du, mut dv) = (zero.clone(), zero.clone(), zero);
+        for j in 0..=q {
+            for i in 0..=p {
+                let c = &self.points[(i, j)];
+                point = point + c.clone() * (bu[i] * bv[j]);
+                du = du + c.clone() * (dbu[i] * bv[j]);
+                dv = dv + c.clone() * (bu[i] * dbv[j]);
+            }
+        }
+
+        (
+            point,
+            du * (T::one() / (self.u.1 - self.u.0)),
+            dv * (T::one() / (self.v.1 - self.v.0)),
+        )
+    }
+
+    /// Split the patch at local position `s` in the u direction.
+    pub fn split_u(&self, s: T) -> (Self, Self) {
+        let (mut left, mut right) = (Vec::new(), Vec::new());
+        let mut u = (self.u, self.u);
+        for j in 0..self.points.height() {
+            let row = Bezier {
+                start: self.u.0,
+                end: self.u.1,
+                points: Vec::from(self.points.row(j)),
+            };
+            let (l, r) = row.split(s);
+            u = ((l.start, l.end), (r.start, r.end));
+            left.extend(l.points);
+            right.extend(r.points);
+        }
+
+        let len = self.points.len();
+        (
+            Self {
+                u: u.0,
+                v: self.v,
+                points: Grid::new(len, left),
+            },
+            Self {
+                u: u.1,
+                v: self.v,
+                points: Grid::new(len, right),
+            },
+        )
+    }
+
+    /// Split the patch into four equal quarters.
+    pub fn quarters(&self) -> [Self; 4] {
+        let half = T::one() / T::cast_from(2);
+        let (left, right) = self.split_u(half);
+        let (a, b) = left.split_v(half);
+        let (c, d) = right.split_v(half);
+        [a, b, c, d]
+    }
+
+    /// Split the patch at local position `t` in the v direction.
+    pub fn split_v(&self, t: T) -> (Self, Self) {
+        let (p, q) = self.degree();
+        let columns: Vec<_> = (0..=p)
+            .map(|i| {
+                let column = Bezier {
+                    start: self.v.0,
+                    end: self.v.1,
+                    points: (0..=q).map(|j| self.points[(i, j)].clone()).collect(),
+                };
+                column.split(t)
+            })
+            .collect();
+
+        let half = |right: bool| {
+            let column = |i: usize| if right { &columns[i].1 } else { &columns[i].0 };
+            let points = (0..=q)
+                .flat_map(|j| (0..=p).map(move |i| column(i).points[j].clone()))
+                .collect();
+            Self {
+                u: self.u,
+                v: (column(0).start, column(0).end),
+                points: Grid::new(p + 1, points),
+            }
+        };
+        (half(false), half(true))
+    }
+}
+
+/// Bernstein basis of `degree` and its derivatives at `t`.
+fn bernstein<T: Scalar>(degree: usize, t: T) -> (Vec<T>, Vec<T>) {
+    let mut basis = Vec::from([T::one()]);
+    let mut derivatives = Vec::from([T::default()]);
+    for p in 1..=degree {
+        let lower = basis;
+        basis = Vec::with_capacity(p + 1);
+        derivatives = Vec::with_capacity(p + 1);
+        for i in 0..=p {
+            let left = if i > 0 { lower[i - 1] } else { T::default() };
+            let right = if i < p { lower[i] } else { T::default() };
+            basis.push(right * (T::one() - t) + left * t);
+            derivatives.push(T::cast_from(p) * (left - right));
+        }
+    }
+    (basis, derivatives)
+}
+
+/// All parameters where a scalar spline, given as Bezier segments, is zero.
+///
+/// Segments are pruned using the convex hull of their coefficients and subdivided
+/// until each contains a single monotone crossing which is then refined with Newton's method.
+pub fn roots<T: Real>(segments: Vec<Bezier<T, T>>) -> Vec<T> {
+    let (Some(first), Some(last)) = (segments.first(), segments.last()) else {
+        return Vec::new();
+    };
+    let tolerance = (last.end - first.start) * T::default_epsilon().sqrt();
+    let scale = segments
+        .iter()
+        .flat_map(|s| s.points.iter())
+        .fold(T::zero(), |scale, &c| scale.max(c.abs()));
+    let zero = scale * T::default_epsilon() * T::cast_from(16);
+
+    let mut roots = Vec::new();
+    for segment in segments {
+        segment_roots(segment, tolerance, zero, &mut roots);
+    }
+    roots.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    roots.dedup_by(|a, b| (*a - *b).abs() <= tolerance);
+    roots
+}
+
+fn segment_roots<T: Real>(segment: Bezier<T, T>, tolerance: T, zero: T, roots: &mut Vec<T>) {
+    let points = &segment.points;
+    let first = points[0];
+    let last = points[points.len() - 1];
+
+    if points.iter().all(|c| c.abs() <= zero) {
+        // the segment lies on the zero set so only its ends are reported
+        roots.push(segment.start);
+        roots.push(segment.end);
+        return;
+    }
+    if first.abs() <= zero {
+        roots.push(segment.start);
+    }
+    if last.abs() <= zero {
+        roots.push(segment.end);
+    }
+
+    let inner = || {
+        let skip_last = usize::from(last.abs() <= zero);
+        points[..points.len() - skip_last]
+            .iter()
+            .skip(usize::from(first.abs() <= zero))
+    };
+    if inner().all(|&c| c > zero) || inner().all(|&c| c < -zero) {
+        return;
+    }
+
+    let derivative = segment.derivative();
+    let monotone = derivative.points.iter().all(|&c| c >= T::zero())
+        || derivative.points.iter().all(|&c| c <= T::zero());
+    if monotone && ((first < -zero && last > zero) || (first > zero && last < -zero)) {
+        roots.push(newton(&segment, &derivative, zero));
+    } else if segment.end - segment.start <= tolerance {
+        roots.push(segment.parameter(T::cast_from(1) / T::cast_from(2)));
+    } else {
+        let (left, right) = segment.split(T::cast_from(1) / T::cast_from(2));
+        segment_roots(left, tolerance, zero, roots);
+        segment_roots(right, tolerance, zero, roots);
+    }
+}
+
+/// Safeguarded Newton iteration for the single root of a monotone segment.
+fn newton<T: Real>(segment: &Bezier<T, T>, derivative: &Bezier<T, T>, zero: T) -> T {
+    let first = segment.points[0];
+    let last = segment.points[segment.points.len() - 1];
+    let width = segment.end - segment.start;
+
+    let (mut low, mut high) = (T::zero(), T::cast_from(1));
+    let mut t = first / (first - last);
+    for _ in 0..64 {
+        let f = segment.at(t);
+        if f.abs() <= zero {
+            break;
+        }
+        if (f < T::zero()) == (first < T::zero()) {
+            low = t;
+        } else {
+            high = t;
+        }
+
+        let mut next = t - f / (derivative.at(t) * width);
+        if !(next > low && next < high) {
+            next = (low + high) / T::cast_from(2);
+        }
+        let step = (next - t).abs();
+        t = next;
+        if step <= T::default_epsilon() {
+            break;
+        }
+    }
+    segment.parameter(t)
+}
+
+/// Split a spline into the Bezier segments covering the range of its knots.
+pub fn decompose<T: Scalar, P: Linear<T>>(
+    degree: usize,
+    mut knots: Vec<T>,
+    mut points: Vec<P>,
+) -> Vec<Bezier<T, P>> {
+    let mut values = Vec::from(&knots[degree..knots.len() - degree]);
+    values.dedup();
+
+    for (i, &u) in values.iter().enumerate() {
+        let multiplicity = knots.iter().filter(|&&k| k == u).count();
+        for _ in multiplicity..degree {
+            // the first value is inserted into the span to its right, everything
+            // else into the span to its left, so that only points in range are used
+            let span = if i == 0 {
+                knots.iter().rposition(|&k| k <= u).unwrap()
+            } else {
+                knots.iter().position(|&k| k == u).unwrap() - 1
+            };
+            insert_knot(degree, &mut knots, &mut points, span, u);
+        }
+    }
+
+    values
+        .windows(2)
+        .map(|w| {
+            let span = knots.iter().rposition(|&k| k <= w[0]).unwrap();
+            Bezier {
+                start: w[0],
+                end: w[1],
+                points: Vec::from(&points[span - degree..=span]),
+            }
+        })
+        .collect()
+}
+
+/// Split a surface into the Bezier patches covering the range of its knots.
+pub fn decompose_surface<T: Scalar, P: Linear<T>>(
+    degree: usize,
+    u_knots: Vec<T>,
+    v_knots: Vec<T>,
+    points: Grid<P>,
+) -> Vec<BezierPatch<T, P>> {
+    let rows: Vec<_> = (0..points.height())
+        .map(|j| decompose(degree, u_knots.clone(), Vec::from(points.row(j))))
+        .collect();
+
+    let mut patches = Vec::new();
+    for s in 0..rows.first().map_or(0, Vec::len) {
+        let columns: Vec<_> = (0..=degree)
+            .map(|i| {
+                let column = rows.iter().map(|row| row[s].points[i].clone()).collect();
+                decompose(degree, v_knots.clone(), column)
+            })
+            .collect();
+
+        for t in 0..columns[0].len() {
+            let points = (0..=degree)
+                .flat_map(|j| columns.iter().map(move |c| c[t].points[j].clone()))
+                .collect();
+            patches.push(BezierPatch {
+                u: (rows[0][s].start, rows[0][s].end),
+                v: (columns[0][t].start, columns[0][t].end),
+                points: Grid::new(degree + 1, points),
+            });
+        }
+    }
+    patches
+}
+
+/// Linear interpolation between `a` and `b`.
+pub fn lerp<T: Scalar, P: Linear<T>>(a: &P, b: &P, t: T) -> P {
+    a.clone() * (T::one() - t) + b.clone() * t
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithms::cox_de_boor_u;
+    use crate::knots::Knots;
+    use alloc::vec;
+    use nalgebra::Vector1;
+
+    #[test]
+    fn it_decomposes_into_matching_segments() {
+        let knots: Vec<f64> = Knots::generate(2, 5);
+        let points = vec![0., 3., -1., 4., 2.];
+        let segments = decompose(2, knots.clone(), points.clone());
+
+        assert_eq!(3, segments.len());
+        for segment in segments {
+            for t in [0., 0.25, 0.5, 1.] {
+                let expected = cox_de_boor_u(
+                    segment.parameter(t),
+                    2,
+                    &Knots::new(2, knots.as_slice()),
+                    |i| Vector1::new(points[i]),
+                );
+                assert!((expected.x - segment.at(t)).abs() < 1e-12);
+            }
+        }
+    }
+
+    #[test]
+    fn it_splits_segments() {
+        let segment = Bezier {
+            start: 0.,
+            end: 2.,
+            points: vec![0f64, 4., 1.],
+        };
+        let (left, right) = segment.split(0.25);
+
+        assert_eq!(0.5, left.end);
+        assert!((segment.at(0.125) - left.at(0.5)).abs() < 1e-12);
+        assert!((segment.at(0.625) - right.at(0.5)).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,505 @@
+use crate::bezier::BezierPatch;
+use crate::export::BoundingBox;
+use crate::intersect::{patch_hit, surface_size, Ray, RayHit};
+use crate::rational::{bounding_box, Patches, RationalSurface};
+use crate::surfaces::UV;
+use crate::types::Real;
+use alloc::vec::Vec;
+use core::ops::RangeInclusive;
+use nalgebra::{Const, Vector3, Vector4};
+
+/// Leaves are subdivided until they are smaller than this fraction of their surface.
+const LEAF: usize = 8;
+/// Maximum number of subdivisions of a patch while building leaves.
+const MAX_DEPTH: usize = 8;
+
+/// Point on a surface nearest to a query point.
+#[derive(Debug, Clone)]
+pub struct Nearest<T> {
+    uv: UV<T>,
+    point: Vector3<T>,
+    distance: T,
+}
+
+impl<T: Real> Nearest<T> {
+    /// Coordinate of the point on the surface.
+    pub fn uv(&self) -> UV<T> {
+        self.uv
+    }
+
+    /// Point on the surface.
+    pub fn point(&self) -> Vector3<T> {
+        self.point
+    }
+
+    /// Distance from the query point.
+    pub fn distance(&self) -> T {
+        self.distance
+    }
+}
+
+/// Piece of a surface stored in the hierarchy.
+#[derive(Debug, Clone)]
+struct Leaf<T: Real> {
+    surface: usize,
+    patch: BezierPatch<T, Vector4<T>>,
+    bbox: BoundingBox<Const<3>, T>,
+}
+
+#[derive(Debug, Clone)]
+enum Node<T: Real> {
+    Leaf(usize),
+    Branch {
+        bbox: BoundingBox<Const<3>, T>,
+        left: usize,
+        right: usize,
+    },
+}
+
+/// Bounding volume hierarchy over the patches of one or more surfaces.
+///
+/// Surfaces are decomposed and subdivided once when inserted, so repeated queries
+/// only visit the pieces whose boxes can contain an answer. Each inserted surface gets its
+/// own balanced subtree, which is joined to the hierarchy without rebuilding the rest.
+/// Results refer to surfaces by the index returned from [`Bvh::insert`].
+#[derive(Debug, Clone)]
+pub struct Bvh<T: Real> {
+    surfaces: Vec<Patches<T>>,
+    sizes: Vec<T>,
+    leaves: Vec<Leaf<T>>,
+    nodes: Vec<Node<T>>,
+}
+
+impl<T: Real> Default for Bvh<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Real> Bvh<T> {
+    /// Empty hierarchy.
+    pub fn new() -> Self {
+        Self {
+            surfaces: Vec::new(),
+            sizes: Vec::new(),
+            leaves: Vec::new(),
+            nodes: Vec::new(),
+        }
+    }
+
+    /// Add a `BSurface` or `NURBSurface` and return its index.
+    pub fn insert(&mut self, surface: &impl RationalSurface<T>) -> usize {
+        let index = self.surfaces.len();
+        let first = self.leaves.len();
+        let patches = Patches::new(surface);
+        let size = surface_size(&patches);
+        let fine = size / T::cast_from(LEAF);
+
+        let mut stack: Vec<_> = patches.patches().iter().map(|p| (p.clone(), 0)).collect();
+        while let Some((patch, depth)) = stack.pop() {
+            let bbox = bounding_box(&patch);
+            if (bbox.max() - bbox.min()).norm() <= fine || depth >= MAX_DEPTH {
+                self.leaves.push(Leaf {
+                    surface: index,
+                    patch,
+                    bbox,
+                });
+            } else {
+                stack.extend(patch.quarters().into_iter().map(|q| (q, depth + 1)));
+            }
+        }
+
+        self.surfaces.push(patches);
+        self.sizes.push(size);
+        let mut leaves: Vec<_> = (first..self.leaves.len()).collect();
+        if !leaves.is_empty() {
+            let subtree = self.split(&mut leaves);
+            self.attach(subtree);
+        }
+        index
+    }
+
+    /// Number of surfaces in the hierarchy.
+    pub fn len(&self) -> usize {
+        self.surfaces.len()
+    }
+
+    /// If no surfaces have been inserted.
+    pub fn is_empty(&self) -> bool {
+        self.surfaces.is_empty()
+    }
+
+    /// Box around every surface in the hierarchy.
+    pub fn bounding_box(&self) -> Option<&BoundingBox<Const<3>, T>> {
+        self.nodes.first().map(|node| self.bbox(node))
+    }
+
+    /// Nearest hit of the ray from `origin` along `direction` with the index of the surface
+    /// hit, or `None` if there is none or the ray is not finite or has no direction.
+    pub fn intersect_ray(
+        &self,
+        origin: Vector3<T>,
+        direction: Vector3<T>,
+    ) -> Option<(usize, RayHit<T>)> {
+        let ray = Ray::new(origin, direction)?;
+        let mut nearest: Option<(usize, RayHit<T>)> = None;
+        let mut stack = self.root();
+
+        while let Some(node) = stack.pop() {
+            let node = &self.nodes[node];
+            let Some(entry) = self.bbox(node).ray_entry(&ray.origin, &ray.direction) else {
+                continue;
+            };
+            let limit = nearest.as_ref().map(|(_, hit)| hit.distance());
+            if limit.is_some_and(|limit| entry > limit) {
+                continue;
+            }
+
+            match node {
+                Node::Leaf(leaf) => {
+                    let leaf = &self.leaves[*leaf];
+                    let surface = &self.surfaces[leaf.surface];
+                    let size = self.sizes[leaf.surface];
+                    if let Some(hit) = patch_hit(surface, &leaf.patch, &ray, limit, size) {
+                        nearest = Some((leaf.surface, hit));
+                    }
+                }
+                Node::Branch { left, right, .. } => stack.extend([*left, *right]),
+            }
+        }
+        nearest
+    }
+
+    /// Point nearest to `point` over all the surfaces with the index of its surface.
+    pub fn nearest(&self, point: Vector3<T>) -> Option<(usize, Nearest<T>)> {
+        let mut nearest: Option<(usize, Nearest<T>)> = None;
+        let mut stack = self.root();
+
+        while let Some(node) = stack.pop() {
+            let node = &self.nodes[node];
+            let limit = nearest.as_ref().map(|(_, n)| n.distance);
+            if limit.is_some_and(|limit| self.bbox(node).distance(&point) >= limit) {
+                continue;
+            }
+
+            match node {
+                Node::Leaf(leaf) => {
+                    let leaf = &self.leaves[*leaf];
+                    let surface = &self.surfaces[leaf.surface];
+                    let uv = surface.closest(&leaf.patch, point);
+                    let on = surface.at(uv);
+                    let distance = (on - point).norm();
+                    if limit.is_none_or(|limit| distance < limit) {
+                        let uv = surface.clamp(uv);
+                        let found = Nearest {
+                            uv,
+                            point: on,
+                            distance,
+                        };
+                        nearest = Some((leaf.surface, found));
+                    }
+                }
+                Node::Branch { left, right, .. } => {
+                    // visit the closer child first
+                    let distance = |n: usize| self.bbox(&self.nodes[n]).distance(&point);
+                    if distance(*left) < distance(*right) {
+                        stack.extend([*right, *left]);
+                    } else {
+                        stack.extend([*left, *right]);
+                    }
+                }
+            }
+        }
+        nearest
+    }
+
+    /// Pieces of the surfaces whose boxes overlap `bbox`, as ranges of coordinates
+    /// together with the index of their surface.
+    pub fn overlapping(
+        &self,
+        bbox: &BoundingBox<Const<3>, T>,
+    ) -> Vec<(usize, UV<RangeInclusive<T>>)> {
+        let mut found = Vec::new();
+        let mut stack = self.root();
+
+        while let Some(node) = stack.pop() {
+            let node = &self.nodes[node];
+            if !self.bbox(node).intersects(bbox) {
+                continue;
+            }
+
+            match node {
+                Node::Leaf(leaf) => {
+                    let leaf = &self.leaves[*leaf];
+                    let (u, v) = (leaf.patch.u, leaf.patch.v);
+                    found.push((leaf.surface, (u.0..=u.1, v.0..=v.1)));
+                }
+                Node::Branch { left, right, .. } => stack.extend([*left, *right]),
+            }
+        }
+        found
+    }
+
+    fn root(&self) -> Vec<usize> {
+        if self.nodes.is_empty() {
+            Vec::new()
+        } else {
+            Vec::from([0])
+        }
+    }
+
+    fn bbox<'a>(&'a self, node: &'a Node<T>) -> &'a BoundingBox<Const<3>, T> {
+        match node {
+            Node::Leaf(leaf) => &self.leaves[*leaf].bbox,
+            Node::Branch { bbox, .. } => bbox,
+        }
+    }
+
+    /// Join the node at `subtree` to the hierarchy as the sibling of the node which adds the
+    /// least surface area of boxes, descending from the root while a child is cheaper.
+    fn attach(&mut self, subtree: usize) {
+        if subtree == 0 {
+            // the first nodes are the root
+            return;
+        }
+        let bbox = self.bbox(&self.nodes[subtree]).clone();
+        let two = T::cast_from(2);
+
+        let mut path = Vec::new();
+        let mut at = 0;
+        while let Node::Branch {
+            bbox: own,
+            left,
+            right,
+        } = &self.nodes[at]
+        {
+            let combined = area(&own.union(&bbox));
+            let sibling = combined * two;
+            let inherited = (combined - area(own)) * two;
+            let descend = |child: usize| {
+                let node = &self.nodes[child];
+                let grown = area(&self.bbox(node).union(&bbox));
+                match node {
+                    Node::Leaf(_) => grown + inherited,
+                    Node::Branch { bbox, .. } => grown - area(bbox) + inherited,
+                }
+            };
+            let (left, right) = (*left, *right);
+            let (left_cost, right_cost) = (descend(left), descend(right));
+            if sibling <= left_cost && sibling <= right_cost {
+                break;
+            }
+            path.push(at);
+            at = if left_cost <= right_cost { left } else { right };
+        }
+
+        // the node keeps its index as the new branch, so it moves to the end
+        let moved = self.nodes.len();
+        self.nodes.push(self.nodes[at].clone());
+        let union = self.bbox(&self.nodes[moved]).union(&bbox);
+        self.nodes[at] = Node::Branch {
+            bbox: union,
+            left: moved,
+            right: subtree,
+        };
+        for &node in path.iter().rev() {
+            if let Node::Branch { left, right, .. } = self.nodes[node] {
+                let refit = self
+                    .bbox(&self.nodes[left])
+                    .union(self.bbox(&self.nodes[right]));
+                self.nodes[node] = Node::Branch {
+                    bbox: refit,
+                    left,
+                    right,
+                };
+            }
+        }
+    }
+
+    /// Add the node over `leaves` and return its index.
+    /// Leaves are split at the median of their centers along the longest axis.
+    fn split(&mut self, leaves: &mut [usize]) -> usize {
+        let index = self.nodes.len();
+        if let [leaf] = leaves {
+            self.nodes.push(Node::Leaf(*leaf));
+            return index;
+        }
+
+        let bbox = leaves[1..]
+            .iter()
+            .fold(self.leaves[leaves[0]].bbox.clone(), |bbox, &l| {
+                bbox.union(&self.leaves[l].bbox)
+            });
+        let axis = (bbox.max() - bbox.min()).imax();
+        leaves.sort_by(|&a, &b| {
+            let a = self.leaves[a].bbox.center()[axis];
+            let b = self.leaves[b].bbox.center()[axis];
+            a.partial_cmp(&b).unwrap()
+        });
+
+        // reserve the branch so that children follow it
+        self.nodes.push(Node::Leaf(0));
+        let (a, b) = leaves.split_at_mut(leaves.len() / 2);
+        let left = self.split(a);
+        let right = self.split(b);
+        self.nodes[index] = Node::Branch { bbox, left, right };
+        index
+    }
+}
+
+/// Surface area of a box.
+fn area<T: Real>(bbox: &BoundingBox<Const<3>, T>) -> T {
+    let d = bbox.max() - bbox.min();
+    (d.x * d.y + d.y * d.z + d.z * d.x) * T::cast_from(2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::control_points::ControlGrid;
+    use crate::surfaces::{BSurface, Surface};
+    use alloc::vec;
+
+    fn plane() -> BSurface<Const<3>, f64> {
+        let mut plane = BSurface::new(ControlGrid::new(
+            1,
+            2,
+            vec![
+                Vector3::new(-4f64, -4., -1.),
+                Vector3::new(4., -4., -1.),
+                Vector3::new(-4., 4., -1.),
+                Vector3::new(4., 4., -1.),
+            ],
+        ));
+        plane.u_knots_mut().clamp_ends();
+        plane.v_knots_mut().clamp_ends();
+        plane
+    }
+
+    fn tube() -> BSurface<Const<3>, f64> {
+        let ring = [(-1f64, -1.), (1., -1.), (1., 1.), (-1., 1.)];
+        let points = (0..4)
+            .flat_map(|z| ring.map(|(x, y)| Vector3::new(x, y, z as f64)))
+            .collect();
+        let mut grid = ControlGrid::new(2, 4, points);
+        grid.set_u_wrapping(true);
+        BSurface::new(grid)
+    }
+
+    fn scene() -> (Bvh<f64>, BSurface<Const<3>, f64>, BSurface<Const<3>, f64>) {
+        let (plane, tube) = (plane(), tube());
+        let mut bvh = Bvh::new();
+        assert_eq!(0, bvh.insert(&plane));
+        assert_eq!(1, bvh.insert(&tube));
+        (bvh, plane, tube)
+    }
+
+    #[test]
+    fn it_hits_the_nearest_surface() {
+        let (bvh, plane, tube) = scene();
+
+        let (surface, hit) = bvh
+            .intersect_ray(Vector3::new(0.1, 0.2, 5.), -Vector3::z())
+            .unwrap();
+        assert_eq!(0, surface);
+        assert!((hit.distance() - 6.).abs() < 1e-9);
+        assert!((plane.at(hit.uv()) - hit.point()).norm() < 1e-9);
+
+        let origin = Vector3::new(-5., 0.1, 1.5);
+        let (surface, hit) = bvh.intersect_ray(origin, Vector3::x()).unwrap();
+        assert_eq!(1, surface);
+        assert!(hit.point().x < 0.);
+        assert!((tube.at(hit.uv()) - hit.point()).norm() < 1e-9);
+        assert_eq!(
+            tube.intersect_ray(origin, Vector3::x()).unwrap().distance(),
+            hit.distance()
+        );
+
+        assert!(bvh.intersect_ray(origin, Vector3::z()).is_none());
+    }
+
+    #[test]
+    fn it_rejects_rays_without_direction() {
+        let (bvh, _, _) = scene();
+        assert!(bvh
+            .intersect_ray(Vector3::new(0.1, 0.2, 5.), Vector3::zeros())
+            .is_none());
+
+        // a ray lying in the plane has no single hit there and passes below the tube
+        assert!(bvh
+            .intersect_ray(Vector3::new(-6., 0.1, -1.), Vector3::x())
+            .is_none());
+    }
+
+    #[test]
+    fn it_finds_nearest_points() {
+        let (bvh, plane, tube) = scene();
+
+        let (surface, nearest) = bvh.nearest(Vector3::new(3., 3., -2.)).unwrap();
+        assert_eq!(0, surface);
+        assert!((nearest.distance() - 1.).abs() < 1e-9);
+        assert!((plane.at(nearest.uv()) - nearest.point()).norm() < 1e-9);
+
+        let (surface, nearest) = bvh.nearest(Vector3::new(0., 0., 1.5)).unwrap();
+        assert_eq!(1, surface);
+        assert!((tube.at(nearest.uv()) - nearest.point()).norm() < 1e-9);
+        assert!((nearest.point().z - 1.5).abs() < 1e-6);
+        assert!((nearest.distance() - 1.).abs() < 1e-6);
+    }
+
+    #[test]
+    fn it_inserts_surfaces_incrementally() {
+        let mut bvh = Bvh::new();
+        let mut planes = Vec::new();
+        for i in 0..12 {
+            let mut plane = plane();
+            let offset = Vector3::new((i % 4) as f64 * 10., (i / 4) as f64 * 10., i as f64);
+            plane.translate(&offset);
+            assert_eq!(i, bvh.insert(&plane));
+            planes.push(plane);
+        }
+        assert_eq!(12, bvh.len());
+
+        for (i, plane) in planes.iter().enumerate() {
+            let above = plane.at((1.5, 1.5)) + Vector3::new(0.5, -0.5, 0.25);
+            let (surface, nearest) = bvh.nearest(above).unwrap();
+            assert_eq!(i, surface);
+            assert!((nearest.distance() - 0.25).abs() < 1e-9);
+
+            let (surface, hit) = bvh.intersect_ray(above, -Vector3::z()).unwrap();
+            assert_eq!(i, surface);
+            assert!((hit.distance() - 0.25).abs() < 1e-9);
+        }
+
+        // every branch covers its children
+        for node in &bvh.nodes {
+            if let Node::Branch { bbox, left, right } = node {
+                for child in [left, right] {
+                    let inner = bvh.bbox(&bvh.nodes[*child]);
+                    assert!(bbox.contains(inner.min()) && bbox.contains(inner.max()));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn it_finds_overlapping_pieces() {
+        let (bvh, _, tube) = scene();
+
+        let probe = BoundingBox::new(&[Vector3::new(0.5, -0.1, 1.4), Vector3::new(2., 0.1, 1.6)]);
+        let found = bvh.overlapping(&probe);
+        assert!(!found.is_empty());
+        assert!(found.iter().all(|(surface, _)| *surface == 1));
+        assert!(found.iter().any(|(_, (u, v))| {
+            let (u, v) = (*u.start(), *v.start());
+            tube.at((u, v)).x > 0.
+        }));
+
+        let far = BoundingBox::new(&[Vector3::new(10., 10., 10.), Vector3::new(11., 11., 11.)]);
+        assert!(bvh.overlapping(&far).is_empty());
+        assert!(bvh
+            .bounding_box()
+            .unwrap()
+            .contains(&Vector3::new(0., 0., 2.)));
+    }
+}
@@ -1,5 +1,6 @@
 mod control_grid;
 mod control_vec;
 
+pub use crate::grid::Grid;
 pub use control_grid::ControlGrid;
 pub use control_vec::ControlVec;
@@ -80,6 +80,62 @@ impl<T> ControlGrid<T> {
     pub fn set_v_wrapping(&mut self, v_wrapping: bool) {
         self.v_wrapping = v_wrapping
     }
+
+    /// The grid with the points in reverse order along u, rotated when wrapping so that
+    /// the wrapped points also come in reverse order.
+    pub(crate) fn reversed_u(&self) -> Self
+    where
+        T: Clone,
+    {
+        let (len, height) = (self.points.len(), self.points.height());
+        let offset = if self.u_wrapping { self.degree } else { 0 };
+        self.reordered(len, height, |u, v| ((len - 1 - u + offset) % len, v))
+    }
+
+    /// The grid with the points in reverse order along v, rotated when wrapping so that
+    /// the wrapped points also come in reverse order.
+    pub(crate) fn reversed_v(&self) -> Self
+    where
+        T: Clone,
+    {
+        let (len, height) = (self.points.len(), self.points.height());
+        let offset = if self.v_wrapping { self.degree } else { 0 };
+        self.reordered(len, height, |u, v| (u, (height - 1 - v + offset) % height))
+    }
+
+    /// The grid with the u and v directions swapped.
+    pub(crate) fn transposed(&self) -> Self
+    where
+        T: Clone,
+    {
+        let mut transposed = self.reordered(self.points.height(), self.points.len(), |u, v| (v, u));
+        transposed.u_wrapping = self.v_wrapping;
+        transposed.v_wrapping = self.u_wrapping;
+        transposed
+    }
+
+    /// New grid of `len` by `height` points where each point at `(u, v)` is taken from
+    /// `source(u, v)` in this grid.
+    fn reordered(
+        &self,
+        len: usize,
+        height: usize,
+        source: impl Fn(usize, usize) -> UV<usize>,
+    ) -> Self
+    where
+        T: Clone,
+    {
+        let points = (0..height)
+            .flat_map(|v| (0..len).map(move |u| (u, v)))
+            .map(|(u, v)| self.points[source(u, v)].clone())
+            .collect();
+        Self {
+            degree: self.degree,
+            points: Grid::new(len, points),
+            u_wrapping: self.u_wrapping,
+            v_wrapping: self.v_wrapping,
+        }
+    }
 }
 
 impl<T> Index<UV<usize>> for ControlGrid<T> {
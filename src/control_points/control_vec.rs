@@ -62,6 +62,23 @@ impl<T> ControlVec<T> {
     pub fn set_wrapping(&mut self, wrapping: bool) {
         self.wrapping = wrapping
     }
+
+    /// The points in reverse order, rotated when wrapping so that the wrapped points
+    /// also come in reverse order.
+    pub(crate) fn reversed(&self) -> Self
+    where
+        T: Clone,
+    {
+        let n = self.points.len();
+        let offset = if self.wrapping { self.degree } else { 0 };
+        Self {
+            degree: self.degree,
+            points: (0..n)
+                .map(|i| self.points[(n - 1 - i + offset) % n].clone())
+                .collect(),
+            wrapping: self.wrapping,
+        }
+    }
 }
 
 impl<T> Index<usize> for ControlVec<T> {
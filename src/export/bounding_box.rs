@@ -1,6 +1,14 @@
-use crate::types::{Scalar, Vector};
+use crate::algorithms::weight;
+use crate::bezier::{roots, Bezier, BezierPatch, Linear};
+use crate::splines::{BSpline, NURBSpline, Spline};
+use crate::surfaces::{BSurface, NURBSurface};
+use crate::types::{Real, Scalar, Vector};
+use alloc::vec::Vec;
 use nalgebra::allocator::Allocator;
-use nalgebra::{DefaultAllocator, Dim};
+use nalgebra::{DefaultAllocator, Dim, DimDiff, DimSub, U1};
+
+/// Maximum number of subdivisions while refining a bounding box.
+const MAX_DEPTH: usize = 32;
 
 /// Axis aligned bounding box.
 #[derive(Debug, Clone)]
@@ -52,14 +60,261 @@ where
     pub fn center(&self) -> Vector<D, T> {
         (&self.min + &self.max) / T::cast_from(2)
     }
+
+    /// Smallest bounding box containing both boxes.
+    pub fn union(&self, other: &Self) -> Self {
+        let mut union = self.clone();
+        for i in 0..self.min.len() {
+            if other.min[i] < union.min[i] {
+                union.min[i] = other.min[i];
+            }
+            if other.max[i] > union.max[i] {
+                union.max[i] = other.max[i];
+            }
+        }
+        union
+    }
+
+    /// If the bounding boxes overlap, including touching.
+    pub fn intersects(&self, other: &Self) -> bool {
+        (0..self.min.len()).all(|i| self.min[i] <= other.max[i] && other.min[i] <= self.max[i])
+    }
+
+    /// If `point` is inside the bounding box, including its boundary.
+    pub fn contains(&self, point: &Vector<D, T>) -> bool {
+        (0..self.min.len()).all(|i| self.min[i] <= point[i] && point[i] <= self.max[i])
+    }
+
+    /// Grow the bounding box by `margin` on every side.
+    pub fn expand(&self, margin: T) -> Self {
+        let mut expanded = self.clone();
+        for i in 0..self.min.len() {
+            expanded.min[i] -= margin;
+            expanded.max[i] += margin;
+        }
+        expanded
+    }
+}
+
+impl<D: Dim, T: Real> BoundingBox<D, T>
+where
+    DefaultAllocator: Allocator<T, D>,
+    <DefaultAllocator as Allocator<T, D>>::Buffer: Default,
+{
+    /// Exact bounding box of a spline, found from the roots of its derivative.
+    pub fn of_spline(spline: &BSpline<D, T>) -> Self {
+        let start = spline.at(*spline.range().start());
+        let mut bbox = Self {
+            min: start.clone(),
+            max: start.clone(),
+        };
+
+        for i in 0..start.len() {
+            for segment in spline.bezier_segments(|p| p[i]) {
+                let width = segment.end - segment.start;
+                let extremes = roots(Vec::from([segment.derivative()]));
+                for u in [segment.start, segment.end].into_iter().chain(extremes) {
+                    let x = segment.at((u - segment.start) / width);
+                    bbox.min[i] = bbox.min[i].min(x);
+                    bbox.max[i] = bbox.max[i].max(x);
+                }
+            }
+        }
+        bbox
+    }
+
+    /// Bounding box of a surface which contains it and is at most `tolerance` larger than exact.
+    pub fn of_surface(surface: &BSurface<D, T>, tolerance: T) -> Self {
+        refine(
+            surface.bezier_patches(Clone::clone),
+            Clone::clone,
+            tolerance,
+        )
+    }
+
+    /// Bounding box of a NURBS spline which contains it and is at most `tolerance` larger than exact.
+    pub fn of_nurbs_spline<W>(spline: &NURBSpline<'_, W, T>, tolerance: T) -> Self
+    where
+        W: Dim + DimSub<U1, Output = D>,
+        DefaultAllocator: Allocator<T, W>,
+    {
+        let segments = spline.spline().bezier_segments(weight);
+        refine(segments, project, tolerance)
+    }
+
+    /// Bounding box of a NURBS surface which contains it and is at most `tolerance` larger than exact.
+    pub fn of_nurbs_surface<W>(surface: &NURBSurface<'_, W, T>, tolerance: T) -> Self
+    where
+        W: Dim + DimSub<U1, Output = D>,
+        DefaultAllocator: Allocator<T, W>,
+    {
+        let patches = surface.spline().bezier_patches(weight);
+        refine(patches, project, tolerance)
+    }
+
+    /// Distance from `point` to the nearest point of the box, zero when inside.
+    pub fn distance(&self, point: &Vector<D, T>) -> T {
+        let mut squared = T::zero();
+        for i in 0..self.min.len() {
+            let d = (self.min[i] - point[i])
+                .max(point[i] - self.max[i])
+                .max(T::zero());
+            squared += d * d;
+        }
+        squared.sqrt()
+    }
+
+    /// Distance along a ray from `origin` in `direction` at which it enters the box.
+    pub(crate) fn ray_entry(&self, origin: &Vector<D, T>, direction: &Vector<D, T>) -> Option<T> {
+        let mut near = T::zero();
+        let mut far = T::max_value()?;
+        for i in 0..self.min.len() {
+            if direction[i] == T::zero() {
+                if origin[i] < self.min[i] || origin[i] > self.max[i] {
+                    return None;
+                }
+                continue;
+            }
+
+            let a = (self.min[i] - origin[i]) / direction[i];
+            let b = (self.max[i] - origin[i]) / direction[i];
+            near = near.max(a.min(b));
+            far = far.min(a.max(b));
+            if near > far {
+                return None;
+            }
+        }
+        Some(near)
+    }
+}
+
+/// Position of a weighted control point.
+fn project<W: DimSub<U1>, T: Real>(point: &Vector<W, T>) -> Vector<DimDiff<W, U1>, T>
+where
+    DefaultAllocator: Allocator<T, W> + Allocator<T, DimDiff<W, U1>>,
+    <DefaultAllocator as Allocator<T, DimDiff<W, U1>>>::Buffer: Default,
+{
+    let mut projected = Vector::<DimDiff<W, U1>, T>::default();
+    let w = point[point.len() - 1];
+    for i in 0..point.len() - 1 {
+        projected[i] = point[i] / w;
+    }
+    projected
+}
+
+/// Bezier piece which lies within the hull of its control points and passes through its corners.
+trait Piece<P>: Sized {
+    fn points(&self) -> &[P];
+    fn corners(&self) -> Vec<&P>;
+    fn subdivide(&self) -> Vec<Self>;
+}
+
+impl<T: Scalar, P: Linear<T>> Piece<P> for Bezier<T, P> {
+    fn points(&self) -> &[P] {
+        &self.points
+    }
+
+    fn corners(&self) -> Vec<&P> {
+        Vec::from([&self.points[0], &self.points[self.points.len() - 1]])
+    }
+
+    fn subdivide(&self) -> Vec<Self> {
+        let (left, right) = self.split(T::one() / T::cast_from(2));
+        Vec::from([left, right])
+    }
+}
+
+impl<T: Scalar, P: Linear<T>> Piece<P> for BezierPatch<T, P> {
+    fn points(&self) -> &[P] {
+        self.points.as_ref()
+    }
+
+    fn corners(&self) -> Vec<&P> {
+        let (p, q) = self.degree();
+        Vec::from([
+            &self.points[(0, 0)],
+            &self.points[(p, 0)],
+            &self.points[(0, q)],
+            &self.points[(p, q)],
+        ])
+    }
+
+    fn subdivide(&self) -> Vec<Self> {
+        Vec::from(self.quarters())
+    }
+}
+
+/// Bound every axis in both directions by subdividing pieces until the hull of
+/// their control points is within `tolerance` of points known to be on the shape.
+fn refine<D: Dim, T: Real, P, S: Piece<P> + Clone>(
+    pieces: Vec<S>,
+    project: impl Fn(&P) -> Vector<D, T>,
+    tolerance: T,
+) -> BoundingBox<D, T>
+where
+    DefaultAllocator: Allocator<T, D>,
+    <DefaultAllocator as Allocator<T, D>>::Buffer: Default,
+{
+    let corner = project(pieces[0].corners()[0]);
+    let mut bbox = BoundingBox {
+        min: corner.clone(),
+        max: corner.clone(),
+    };
+
+    for i in 0..corner.len() {
+        for sign in [T::cast_from(1), -T::cast_from(1)] {
+            let value = |p: &P| project(p)[i] * sign;
+            let corners = |piece: &S| {
+                piece
+                    .corners()
+                    .into_iter()
+                    .map(value)
+                    .fold(T::min_value().unwrap(), |a, b| a.max(b))
+            };
+
+            let mut known = pieces
+                .iter()
+                .map(&corners)
+                .fold(T::min_value().unwrap(), |a, b| a.max(b));
+            let mut bound = known;
+            let mut stack: Vec<_> = pieces.iter().map(|p| (p.clone(), 0)).collect();
+            while let Some((piece, depth)) = stack.pop() {
+                let upper = piece
+                    .points()
+                    .iter()
+                    .map(value)
+                    .fold(T::min_value().unwrap(), |a, b| a.max(b));
+                if upper <= bound {
+                    continue;
+                }
+                if upper <= known + tolerance || depth >= MAX_DEPTH {
+                    bound = upper;
+                    continue;
+                }
+
+                for child in piece.subdivide() {
+                    known = known.max(corners(&child));
+                    stack.push((child, depth + 1));
+                }
+            }
+
+            if sign > T::zero() {
+                bbox.max[i] = bound;
+            } else {
+                bbox.min[i] = -bound;
+            }
+        }
+    }
+    bbox
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::splines::{BSpline, Spline};
-    use alloc::vec::Vec;
-    use nalgebra::Vector3;
+    use crate::control_points::{ControlGrid, ControlVec};
+    use crate::surfaces::Surface;
+    use alloc::vec;
+    use nalgebra::{Vector2, Vector3};
 
     #[test]
     fn it_calculates_for_control_points() {
@@ -79,4 +334,76 @@ mod tests {
         assert_ne!(*bbox.min(), Vector3::default());
         assert_ne!(*bbox.max(), Vector3::default());
     }
+
+    #[test]
+    fn it_calculates_exactly_for_splines() {
+        let mut spline = BSpline::new(ControlVec::new(
+            2,
+            vec![
+                Vector2::new(0., 0.),
+                Vector2::new(1., 2.),
+                Vector2::new(2., 0.),
+            ],
+        ));
+        spline.knots_mut().clamp_ends();
+
+        let bbox = BoundingBox::of_spline(&spline);
+
+        assert!((bbox.min() - Vector2::new(0., 0.)).norm() < 1e-9);
+        assert!((bbox.max() - Vector2::new(2., 1.)).norm() < 1e-9);
+    }
+
+    #[test]
+    fn it_calculates_for_nurbs_splines() {
+        let circle = BSpline::circle();
+
+        let bbox = BoundingBox::of_nurbs_spline(&circle.nurbs(), 1e-6);
+
+        assert!((bbox.min() - Vector2::new(-0.5, -0.5)).norm() < 1e-5);
+        assert!((bbox.max() - Vector2::new(0.5, 0.5)).norm() < 1e-5);
+        for point in circle.nurbs().quantize(0.01) {
+            assert!(bbox.contains(&point));
+        }
+    }
+
+    #[test]
+    fn it_calculates_for_surfaces() {
+        let surface = BSurface::new(ControlGrid::new(
+            2,
+            3,
+            vec![
+                Vector3::new(0., 0., 0.),
+                Vector3::new(1., 0., 1.),
+                Vector3::new(2., 0., 0.),
+                Vector3::new(0., 1., 1.),
+                Vector3::new(1., 1., 4.),
+                Vector3::new(2., 1., 1.),
+                Vector3::new(0., 2., 0.),
+                Vector3::new(1., 2., 1.),
+                Vector3::new(2., 2., 0.),
+            ],
+        ));
+
+        let bbox = BoundingBox::of_surface(&surface, 1e-6);
+        let points: Vec<_> = surface.quantize(0.01).collect();
+        let sampled = BoundingBox::new(&points);
+
+        assert!(bbox.expand(1e-9).contains(sampled.min()));
+        assert!(bbox.expand(1e-9).contains(sampled.max()));
+        assert!((bbox.max() - sampled.max()).norm() < 1e-3);
+        assert!((bbox.min() - sampled.min()).norm() < 1e-3);
+    }
+
+    #[test]
+    fn it_combines_boxes() {
+        let a = BoundingBox::new(&[Vector2::new(0., 0.), Vector2::new(1., 1.)]);
+        let b = BoundingBox::new(&[Vector2::new(2., 2.), Vector2::new(3., 3.)]);
+
+        assert!(!a.intersects(&b));
+        assert!(a.expand(0.5).intersects(&b.expand(0.5)));
+        assert!(a.union(&b).contains(&Vector2::new(1.5, 1.5)));
+        assert!(!a.contains(&Vector2::new(1.5, 1.5)));
+        assert_eq!(0., a.distance(&Vector2::new(0.5, 0.5)));
+        assert!((a.distance(&Vector2::new(4., 5.)) - 5f64).abs() < 1e-12);
+    }
 }
@@ -1,8 +1,11 @@
+use crate::algorithms::distance_to_segment;
+use crate::export::BoundingBox;
 use crate::grid::Grid;
-use crate::surfaces::Surface;
-use crate::types::{Scalar, Vector};
+use crate::surfaces::{Surface, TrimmedSurface};
+use crate::types::{Real, Scalar, Vector};
+use alloc::collections::BTreeMap;
 use alloc::vec::Vec;
-use nalgebra::{Const, Vector3};
+use nalgebra::{Const, Matrix3, Vector2, Vector3};
 
 type IndexedTriangle = [usize; 3];
 type Triangle<T> = [Vector3<T>; 3];
@@ -73,6 +76,13 @@ impl<T: Scalar> Triangulation<T> {
         a.cross(&b)
     }
 
+    fn normal_of(points: &[Vector3<T>], triangle: IndexedTriangle) -> Vector3<T> {
+        let a = points[triangle[1]] - points[triangle[0]];
+        let b = points[triangle[2]] - points[triangle[0]];
+
+        a.cross(&b)
+    }
+
     /// Points in the triangulation.
     pub fn points(&self) -> &[Vector3<T>] {
         &self.points
@@ -104,3 +114,512 @@ impl<T: Scalar> Triangulation<T> {
             .map(|(i, t)| (t, self.normals[i]))
     }
 }
+
+impl<T: Real> Triangulation<T> {
+    /// Create a new Triangulation of only the trimmed region of a surface.
+    /// `step` is the amount of detail generated, triangle edges along the loops are at most
+    /// half of it apart so that the mesh follows them closely.
+    pub fn trimmed<S: Surface<Const<3>, T>>(step: T, surface: &TrimmedSurface<S, T>) -> Self {
+        let half = step / T::cast_from(2);
+        let boundary = surface.boundary(half);
+        let mut uvs: Vec<_> = boundary.iter().flatten().copied().collect();
+        let mut constraints = Vec::with_capacity(uvs.len());
+        let mut first = 0;
+        for l in &boundary {
+            constraints.extend((0..l.len()).map(|i| (first + i, first + (i + 1) % l.len())));
+            first += l.len();
+        }
+
+        // grid points inside the region which are not too close to the loops
+        let edges = EdgeIndex::new(&uvs, &constraints, half);
+        let inner = surface.surface();
+        for v in inner.quantize_v_range(step) {
+            for u in inner.quantize_u_range(step) {
+                let uv = Vector2::new(u, v);
+                if edges.clear(&uvs, &uv) && surface.contains((u, v)) {
+                    uvs.push(uv);
+                }
+            }
+        }
+
+        // the loops are kept as edges so no triangle crosses them, those outside are dropped
+        let mut triangles = delaunay(&uvs, &constraints);
+        triangles.retain(|t| {
+            let center = (uvs[t[0]] + uvs[t[1]] + uvs[t[2]]) / T::cast_from(3);
+            surface.contains((center.x, center.y))
+        });
+
+        // keep only the points used by triangles
+        let mut index = alloc::vec![None; uvs.len()];
+        let mut points = Vec::new();
+        for triangle in triangles.iter_mut() {
+            for i in triangle.iter_mut() {
+                *i = *index[*i].get_or_insert_with(|| {
+                    points.push(inner.at((uvs[*i].x, uvs[*i].y)));
+                    points.len() - 1
+                });
+            }
+        }
+        let normals = triangles
+            .iter()
+            .map(|&t| Self::normal_of(&points, t))
+            .collect();
+
+        Self {
+            points,
+            normals,
+            indexed_triangles: triangles,
+        }
+    }
+}
+
+/// Edges between points bucketed into square cells, for finding the edges near a point.
+struct EdgeIndex<T> {
+    origin: Vector2<T>,
+    size: T,
+    cells: BTreeMap<(usize, usize), Vec<(usize, usize)>>,
+}
+
+impl<T: Real> EdgeIndex<T> {
+    /// Index of `edges` between `points` in cells of `size`, at least as large as every edge.
+    fn new(points: &[Vector2<T>], edges: &[(usize, usize)], size: T) -> Self {
+        let origin = if points.is_empty() {
+            Vector2::zeros()
+        } else {
+            BoundingBox::new(points).min() - Vector2::repeat(size)
+        };
+        let mut index = Self {
+            origin,
+            size,
+            cells: BTreeMap::new(),
+        };
+        for &(a, b) in edges {
+            let (min, max) = (index.cell(&points[a]), index.cell(&points[b]));
+            for x in min.0.min(max.0)..=min.0.max(max.0) {
+                for y in min.1.min(max.1)..=min.1.max(max.1) {
+                    index.cells.entry((x, y)).or_default().push((a, b));
+                }
+            }
+        }
+        index
+    }
+
+    fn cell(&self, point: &Vector2<T>) -> (usize, usize) {
+        let at = |x: T| -> usize { (x / self.size).floor().max(T::zero()).cast() };
+        let offset = point - self.origin;
+        (at(offset.x), at(offset.y))
+    }
+
+    /// If `point` is at least the cell size away from every edge. Such edges have a point
+    /// in the cell of `point` or one of its neighbours.
+    fn clear(&self, points: &[Vector2<T>], point: &Vector2<T>) -> bool {
+        let (x, y) = self.cell(point);
+        (x.saturating_sub(1)..=x + 1).all(|x| {
+            (y.saturating_sub(1)..=y + 1).all(|y| {
+                self.cells.get(&(x, y)).is_none_or(|edges| {
+                    edges.iter().all(|&(a, b)| {
+                        distance_to_segment(point, &points[a], &points[b]) >= self.size
+                    })
+                })
+            })
+        })
+    }
+}
+
+/// Triangles of a triangulation, removed by leaving gaps, with each directed edge mapped
+/// to the triangle on its left.
+struct Mesh {
+    triangles: Vec<Option<IndexedTriangle>>,
+    edges: BTreeMap<(usize, usize), usize>,
+}
+
+impl Mesh {
+    fn add(&mut self, t: IndexedTriangle) {
+        for e in 0..3 {
+            self.edges
+                .insert((t[e], t[(e + 1) % 3]), self.triangles.len());
+        }
+        self.triangles.push(Some(t));
+    }
+
+    fn remove(&mut self, index: usize) -> IndexedTriangle {
+        let t = self.triangles[index].take().unwrap();
+        for e in 0..3 {
+            self.edges.remove(&(t[e], t[(e + 1) % 3]));
+        }
+        t
+    }
+
+    /// Triangle on the left of the edge from `a` to `b`, rotated to start with `a`.
+    fn left_of(&self, a: usize, b: usize) -> Option<(usize, IndexedTriangle)> {
+        let index = *self.edges.get(&(a, b))?;
+        let t = self.triangles[index].unwrap();
+        let e = t.iter().position(|&i| i == a).unwrap();
+        Some((index, [t[e], t[(e + 1) % 3], t[(e + 2) % 3]]))
+    }
+}
+
+/// Constrained Delaunay triangulation of `points` with triangles counter clockwise, using the
+/// Bowyer-Watson algorithm and then flipping edges until each of the `constraints` between
+/// points is an edge. Points equal to an earlier point are merged into it and so are not in
+/// any triangle, every other point is.
+fn delaunay<T: Real>(
+    points: &[Vector2<T>],
+    constraints: &[(usize, usize)],
+) -> Vec<IndexedTriangle> {
+    if points.len() < 3 {
+        return Vec::new();
+    }
+    assert!(
+        points.iter().all(|p| p.x.is_finite() && p.y.is_finite()),
+        "points must be finite"
+    );
+
+    // start from a triangle far around every point
+    let bbox = BoundingBox::new(points);
+    let center = bbox.center();
+    let size = (bbox.max() - bbox.min()).norm().max(T::cast_from(1)) * T::cast_from(16);
+    let n = points.len();
+    let mut vertices = points.to_vec();
+    vertices.push(center + Vector2::new(-size * T::cast_from(3), -size));
+    vertices.push(center + Vector2::new(size * T::cast_from(3), -size));
+    vertices.push(center + Vector2::new(T::zero(), size * T::cast_from(2)));
+
+    let mut mesh = Mesh {
+        triangles: Vec::new(),
+        edges: BTreeMap::new(),
+    };
+    mesh.add([n, n + 1, n + 2]);
+
+    let mut merged: Vec<usize> = (0..n).collect();
+    for i in 0..n {
+        let point = vertices[i];
+        let start = locate(&vertices, &mesh, mesh.triangles.len() - 1, &point);
+        let enclosing = mesh.triangles[start].unwrap();
+        if let Some(&same) = enclosing.iter().find(|&&j| vertices[j] == point) {
+            merged[i] = same;
+            continue;
+        }
+
+        // grow the cavity from the enclosing triangle so that it stays connected
+        let mut cavity = Vec::from([start]);
+        let mut stack = Vec::from([start]);
+        while let Some(t) = stack.pop() {
+            let t = mesh.triangles[t].unwrap();
+            for e in 0..3 {
+                if let Some(&neighbour) = mesh.edges.get(&(t[(e + 1) % 3], t[e])) {
+                    let inside = in_circle(&vertices, mesh.triangles[neighbour].unwrap(), &point);
+                    if inside && !cavity.contains(&neighbour) {
+                        cavity.push(neighbour);
+                        stack.push(neighbour);
+                    }
+                }
+            }
+        }
+
+        // edges around the cavity are those not shared between its triangles
+        let mut around: Vec<(usize, usize)> = Vec::new();
+        for &t in &cavity {
+            let t = mesh.remove(t);
+            for e in 0..3 {
+                let edge = (t[e], t[(e + 1) % 3]);
+                match around.iter().position(|&(a, b)| (b, a) == edge) {
+                    Some(shared) => {
+                        around.swap_remove(shared);
+                    }
+                    None => around.push(edge),
+                }
+            }
+        }
+        // the next point is searched for from the last of these, near this one
+        for (a, b) in around {
+            mesh.add([a, b, i]);
+        }
+    }
+
+    for &(a, b) in constraints {
+        let (a, b) = (merged[a], merged[b]);
+        if a != b {
+            constrain(&vertices, &mut mesh, a, b);
+        }
+    }
+
+    mesh.triangles
+        .into_iter()
+        .flatten()
+        .filter(|t| t.iter().all(|&i| i < n))
+        .collect()
+}
+
+/// Index of a triangle enclosing `point`, walking across the edges `point` is beyond from
+/// the triangle at `start`. Falls back to searching every triangle should the walk cycle.
+fn locate<T: Real>(
+    vertices: &[Vector2<T>],
+    mesh: &Mesh,
+    start: usize,
+    point: &Vector2<T>,
+) -> usize {
+    let mut current = start;
+    for _ in 0..mesh.triangles.len() {
+        let t = mesh.triangles[current].unwrap();
+        let beyond = (0..3).find_map(|e| {
+            let (a, b) = (t[e], t[(e + 1) % 3]);
+            let outside = (vertices[b] - vertices[a]).perp(&(point - vertices[a])) < T::zero();
+            outside.then(|| mesh.edges.get(&(b, a))).flatten()
+        });
+        match beyond {
+            Some(&next) => current = next,
+            None => return current,
+        }
+    }
+    mesh.triangles
+        .iter()
+        .position(|t| t.is_some_and(|t| encloses(vertices, t, point)))
+        .unwrap()
+}
+
+/// Flip edges until the segment from `a` to `b` is an edge of the mesh, after Sloan. Where
+/// the segment runs through another vertex it is split there.
+fn constrain<T: Real>(vertices: &[Vector2<T>], mesh: &mut Mesh, a: usize, b: usize) {
+    let orient = |p: usize, q: usize, r: usize| {
+        (vertices[q] - vertices[p]).perp(&(vertices[r] - vertices[p]))
+    };
+    let zero = T::zero();
+    let mut from = a;
+    while from != b {
+        if mesh.edges.contains_key(&(from, b)) || mesh.edges.contains_key(&(b, from)) {
+            return;
+        }
+
+        // the triangle around `from` the segment leaves through, or a vertex on the segment
+        let mut crossing = None;
+        let mut through = None;
+        for &(_, x) in mesh.edges.range((from, 0)..(from + 1, 0)).map(|(e, _)| e) {
+            let (_, [_, x, y]) = mesh.left_of(from, x).unwrap();
+            let ahead = (vertices[x] - vertices[from]).dot(&(vertices[b] - vertices[from])) > zero;
+            if orient(from, b, x) == zero && ahead {
+                through = Some(x);
+                break;
+            }
+            if orient(from, b, x) < zero && orient(from, b, y) > zero {
+                crossing = Some((x, y));
+                break;
+            }
+        }
+        let Some((mut right, mut left)) = crossing else {
+            // runs along an edge to a vertex on the segment, or there is nothing to cross
+            match through {
+                Some(x) => {
+                    from = x;
+                    continue;
+                }
+                None => return,
+            }
+        };
+
+        // edges crossed by the segment up to `b` or the next vertex on it
+        let mut crossed = Vec::new();
+        let to = loop {
+            crossed.push((right, left));
+            let (_, [_, _, z]) = mesh.left_of(left, right).unwrap();
+            if z == b || orient(from, b, z) == zero {
+                break z;
+            }
+            if orient(from, b, z) < zero {
+                right = z;
+            } else {
+                left = z;
+            }
+        };
+
+        // flip the crossed edges, retrying those whose quadrilateral is not yet convex
+        let mut queue = alloc::collections::VecDeque::from(crossed);
+        let mut stalled = 0;
+        while let Some((x, y)) = queue.pop_front() {
+            let (first, [_, _, p]) = mesh.left_of(x, y).unwrap();
+            let (second, [_, _, q]) = mesh.left_of(y, x).unwrap();
+            let convex = orient(p, q, x) * orient(p, q, y) < zero;
+            if !convex {
+                queue.push_back((x, y));
+                stalled += 1;
+                assert!(stalled <= 2 * queue.len() + 2, "constraints must not cross");
+                continue;
+            }
+            stalled = 0;
+            mesh.remove(first);
+            mesh.remove(second);
+            mesh.add([x, q, p]);
+            mesh.add([q, y, p]);
+            let crosses = orient(from, to, p) * orient(from, to, q) < zero
+                && orient(p, q, from) * orient(p, q, to) < zero;
+            if crosses {
+                queue.push_back((p, q));
+            }
+        }
+        from = to;
+    }
+}
+
+/// If `point` is inside or on the edges of the counter clockwise `triangle`.
+fn encloses<T: Real>(
+    vertices: &[Vector2<T>],
+    triangle: IndexedTriangle,
+    point: &Vector2<T>,
+) -> bool {
+    (0..3).all(|e| {
+        let a = vertices[triangle[e]];
+        let b = vertices[triangle[(e + 1) % 3]];
+        (b - a).perp(&(point - a)) >= T::zero()
+    })
+}
+
+/// If `point` is inside the circle through the counter clockwise `triangle`.
+fn in_circle<T: Real>(
+    vertices: &[Vector2<T>],
+    triangle: IndexedTriangle,
+    point: &Vector2<T>,
+) -> bool {
+    let row = |i: usize| {
+        let d = vertices[triangle[i]] - point;
+        [d.x, d.y, d.norm_squared()]
+    };
+    let (a, b, c) = (row(0), row(1), row(2));
+    Matrix3::new(a[0], a[1], a[2], b[0], b[1], b[2], c[0], c[1], c[2]).determinant() > T::zero()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::control_points::{ControlGrid, ControlVec};
+    use crate::splines::BSpline;
+    use crate::surfaces::BSurface;
+    use alloc::vec;
+
+    fn square(min: f64, max: f64) -> Vec<BSpline<Const<2>, f64>> {
+        polygon(&[(min, min), (max, min), (max, max), (min, max)])
+    }
+
+    fn polygon(corners: &[(f64, f64)]) -> Vec<BSpline<Const<2>, f64>> {
+        (0..corners.len())
+            .map(|i| {
+                let (a, b) = (corners[i], corners[(i + 1) % corners.len()]);
+                let mut line = BSpline::new(ControlVec::new(
+                    1,
+                    vec![Vector2::new(a.0, a.1), Vector2::new(b.0, b.1)],
+                ));
+                line.knots_mut().clamp_ends();
+                line
+            })
+            .collect()
+    }
+
+    #[test]
+    fn it_triangulates_trimmed_surfaces() {
+        let mut plane = BSurface::new(ControlGrid::new(
+            1,
+            2,
+            vec![
+                Vector3::new(0f64, 0., 0.),
+                Vector3::new(4., 0., 0.),
+                Vector3::new(0., 4., 0.),
+                Vector3::new(4., 4., 0.),
+            ],
+        ));
+        plane.u_knots_mut().clamp_ends();
+        plane.v_knots_mut().clamp_ends();
+
+        let mut trimmed = TrimmedSurface::new(plane, square(1.1, 1.9));
+        trimmed.add_hole(square(1.3, 1.6));
+        let t = Triangulation::trimmed(0.05, &trimmed);
+
+        // the area of the mesh matches the region between the loops
+        let area = t.normals().iter().map(|n| n.norm() / 2.).sum::<f64>();
+        let expected = 16. * (0.8 * 0.8 - 0.3 * 0.3);
+        assert!((area - expected).abs() < 1e-9);
+
+        for (triangle, normal) in t.triangles_with_normals() {
+            assert!(normal.z > 0.);
+            let center = (triangle[0] + triangle[1] + triangle[2]) / 3.;
+            assert!(trimmed.contains((center.x / 4. + 1., center.y / 4. + 1.)));
+        }
+    }
+
+    #[test]
+    fn it_keeps_constrained_edges() {
+        // the edge between the first two points is cut across by the Delaunay edge between
+        // the other two, which lie close below and further above it
+        let points = [
+            Vector2::new(0., 1.),
+            Vector2::new(1., 1.),
+            Vector2::new(0.5, 0.98),
+            Vector2::new(0.5, 1.5),
+        ];
+        let n = points.len();
+        let constraints = [(0, 1)];
+        let triangles = delaunay(&points, &constraints);
+
+        let has_edge = |a: usize, b: usize| {
+            triangles
+                .iter()
+                .any(|t| (0..3).any(|e| (t[e], t[(e + 1) % 3]) == (a, b)))
+        };
+        for &(a, b) in &constraints {
+            assert!(has_edge(a, b));
+        }
+        for i in 0..n {
+            assert!(triangles.iter().any(|t| t.contains(&i)));
+        }
+        for t in &triangles {
+            let (a, b, c) = (points[t[0]], points[t[1]], points[t[2]]);
+            assert!((b - a).perp(&(c - a)) > 0.);
+        }
+    }
+
+    #[test]
+    fn it_merges_equal_points() {
+        let points = [
+            Vector2::new(0., 0.),
+            Vector2::new(1., 0.),
+            Vector2::new(0., 1.),
+            Vector2::new(1., 0.),
+            Vector2::new(1., 1.),
+        ];
+        let triangles = delaunay(&points, &[(0, 3), (3, 4)]);
+        assert_eq!(2, triangles.len());
+        assert!(triangles.iter().all(|t| !t.contains(&3)));
+    }
+
+    #[test]
+    fn it_follows_concave_loops() {
+        let mut plane = BSurface::new(ControlGrid::new(
+            1,
+            2,
+            vec![
+                Vector3::new(0f64, 0., 0.),
+                Vector3::new(4., 0., 0.),
+                Vector3::new(0., 4., 0.),
+                Vector3::new(4., 4., 0.),
+            ],
+        ));
+        plane.u_knots_mut().clamp_ends();
+        plane.v_knots_mut().clamp_ends();
+
+        // a narrow slit whose sides are sampled out of step with each other
+        let outline = polygon(&[
+            (1., 1.),
+            (2., 1.),
+            (2., 1.5),
+            (1.2, 1.5),
+            (1.2, 1.502),
+            (2., 1.51),
+            (2., 2.),
+            (1., 2.),
+        ]);
+        let trimmed = TrimmedSurface::new(plane, outline);
+        let t = Triangulation::trimmed(0.1, &trimmed);
+
+        let area = t.normals().iter().map(|n| n.norm() / 2.).sum::<f64>();
+        let expected = 16. * (1. - 0.8 * (0.002 + 0.01) / 2.);
+        assert!((area - expected).abs() < 1e-9);
+    }
+}
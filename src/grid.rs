@@ -1,6 +1,7 @@
 use alloc::vec::Vec;
 use core::ops::{Index, IndexMut};
 
+/// 2D grid of values stored row by row.
 #[derive(Debug, Clone)]
 pub struct Grid<T> {
     len: usize,
@@ -15,6 +16,7 @@ impl<T> Grid<T> {
         }
     }
 
+    /// Construct a grid from rows of `len` values.
     pub fn new(len: usize, values: Vec<T>) -> Self {
         assert_eq!(
             0,
@@ -33,18 +35,32 @@ impl<T> Grid<T> {
         &self.values[index]
     }
 
+    /// Number of values in each row.
     pub fn len(&self) -> usize {
         self.len
     }
 
+    /// Whether the grid has no values.
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Number of rows.
     pub fn height(&self) -> usize {
         self.values.len() / self.len
     }
 
+    /// Values of the row at index `row`.
+    pub fn row(&self, row: usize) -> &[T] {
+        &self.values[(row * self.len)..(row * self.len) + self.len]
+    }
+
+    /// Mutable values of the row at index `row`.
     pub fn row_mut(&mut self, row: usize) -> &mut [T] {
         &mut self.values[(row * self.len)..(row * self.len) + self.len]
     }
 
+    /// Index into the values stored row by row of the value at `(col, row)`.
     pub fn vec_index(&self, (col, row): (usize, usize)) -> usize {
         (row * self.len) + col
     }
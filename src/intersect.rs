@@ -0,0 +1,35 @@
+mod contour;
+mod curve;
+mod ray;
+mod surface;
+
+use crate::splines::BSpline;
+use crate::types::Real;
+use alloc::vec::Vec;
+use nalgebra::{Const, Vector3};
+
+pub use contour::Contour;
+pub use ray::RayHit;
+
+pub(crate) use ray::{patch_hit, surface_size, Ray};
+pub use surface::SurfaceIntersection;
+
+/// Cubic BSpline passing within `tolerance` of every one of `points`, or of a lower degree
+/// when there are too few of them. A closed one starts and ends at the first point.
+/// Returned with the largest distance from a point to the spline, or `None` if the fit
+/// cannot be solved.
+fn fit<T: Real>(
+    points: &[Vector3<T>],
+    closed: bool,
+    tolerance: T,
+) -> Option<(BSpline<Const<3>, T>, T)> {
+    let mut points = Vec::from(points);
+    if closed {
+        points.extend(points.first().copied());
+    }
+    let degree = points.len().saturating_sub(1).min(3);
+    if degree == 0 {
+        return None;
+    }
+    BSpline::fit(&points, degree, tolerance)
+}
@@ -0,0 +1,332 @@
+use crate::intersect::fit;
+use crate::splines::BSpline;
+use crate::surfaces::{BSurface, NURBSurface, Surface, UV};
+use crate::types::Real;
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::ops::RangeInclusive;
+use nalgebra::{Const, Vector3};
+
+/// Curve where a surface meets a plane.
+#[derive(Debug, Clone)]
+pub struct Contour<T> {
+    uv: Vec<UV<T>>,
+    points: Vec<Vector3<T>>,
+    closed: bool,
+}
+
+impl<T: Real> Contour<T> {
+    /// Points of the contour in the parameter space of the surface.
+    pub fn uv(&self) -> &[UV<T>] {
+        &self.uv
+    }
+
+    /// Points of the contour in 3D.
+    pub fn points(&self) -> &[Vector3<T>] {
+        &self.points
+    }
+
+    /// If the contour forms a loop. The last point then connects back to the first.
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+
+    /// Cubic BSpline passing within `tolerance` of every point of the contour, starting and
+    /// ending at the first point of a closed one. Returned with the largest distance from a
+    /// point to the spline, or `None` if the fit cannot be solved.
+    pub fn spline(&self, tolerance: T) -> Option<(BSpline<Const<3>, T>, T)> {
+        fit(&self.points, self.closed, tolerance)
+    }
+}
+
+impl<T: Real> BSurface<Const<3>, T> {
+    /// Curves where the surface meets the plane through `origin` with `normal`.
+    /// `step` is the spacing of the parameter grid used to trace them, and must be positive.
+    pub fn intersect_plane(
+        &self,
+        origin: Vector3<T>,
+        normal: Vector3<T>,
+        step: T,
+    ) -> Vec<Contour<T>> {
+        let grid = self.control_grid();
+        contours(
+            self,
+            (grid.u_wrapping(), grid.v_wrapping()),
+            |p| (p - origin).dot(&normal),
+            step,
+        )
+    }
+}
+
+impl<T: Real> NURBSurface<'_, Const<4>, T> {
+    /// Curves where the surface meets the plane through `origin` with `normal`.
+    /// `step` is the spacing of the parameter grid used to trace them, and must be positive.
+    pub fn intersect_plane(
+        &self,
+        origin: Vector3<T>,
+        normal: Vector3<T>,
+        step: T,
+    ) -> Vec<Contour<T>> {
+        let grid = self.spline().control_grid();
+        contours(
+            self,
+            (grid.u_wrapping(), grid.v_wrapping()),
+            |p| (p - origin).dot(&normal),
+            step,
+        )
+    }
+}
+
+/// Grid edge identified by its direction and first corner.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Edge {
+    U(usize, usize),
+    V(usize, usize),
+}
+
+/// Trace the zero level set of `field` over a surface using marching squares.
+fn contours<T: Real>(
+    surface: &impl Surface<Const<3>, T>,
+    (u_wrapping, v_wrapping): (bool, bool),
+    field: impl Fn(Vector3<T>) -> T,
+    step: T,
+) -> Vec<Contour<T>> {
+    let us = samples(surface.u_range(), step);
+    let vs = samples(surface.v_range(), step);
+    let (nu, nv) = (us.len(), vs.len());
+    let f = |uv: UV<T>| field(surface.at(uv));
+
+    let mut values = Vec::with_capacity(nu * nv);
+    for j in 0..nv {
+        for i in 0..nu {
+            // wrapped ends share their values so that both sides agree on crossings
+            let i = if u_wrapping && i == nu - 1 { 0 } else { i };
+            let j = if v_wrapping && j == nv - 1 { 0 } else { j };
+            values.push(f((us[i], vs[j])));
+        }
+    }
+    let value = |i: usize, j: usize| values[j * nu + i];
+    let inside = |i: usize, j: usize| value(i, j) >= T::zero();
+
+    // edges on the wrapped end of the grid are the same as on the start
+    let canonical = |edge: Edge| match edge {
+        Edge::V(i, j) if u_wrapping && i == nu - 1 => Edge::V(0, j),
+        Edge::U(i, j) if v_wrapping && j == nv - 1 => Edge::U(i, 0),
+        edge => edge,
+    };
+
+    let mut crossings = BTreeMap::new();
+    let mut crossing = |edge: Edge| {
+        let edge = canonical(edge);
+        crossings.entry(edge).or_insert_with(|| {
+            let (a, b) = match edge {
+                Edge::U(i, j) => ((i, j), (i + 1, j)),
+                Edge::V(i, j) => ((i, j), (i, j + 1)),
+            };
+            refine(
+                f,
+                ((us[a.0], vs[a.1]), value(a.0, a.1)),
+                ((us[b.0], vs[b.1]), value(b.0, b.1)),
+            )
+        });
+        edge
+    };
+
+    let mut segments = Vec::new();
+    for j in 0..nv - 1 {
+        for i in 0..nu - 1 {
+            // corners and edges counter clockwise from (i, j)
+            let corners = [
+                inside(i, j),
+                inside(i + 1, j),
+                inside(i + 1, j + 1),
+                inside(i, j + 1),
+            ];
+            let edges = [
+                Edge::U(i, j),
+                Edge::V(i + 1, j),
+                Edge::U(i, j + 1),
+                Edge::V(i, j),
+            ];
+            let crossed: Vec<_> = (0..4)
+                .filter(|&e| corners[e] != corners[(e + 1) % 4])
+                .map(|e| crossing(edges[e]))
+                .collect();
+
+            match crossed.len() {
+                2 => segments.push((crossed[0], crossed[1])),
+                4 => {
+                    // saddle, use the center to decide which corners are connected
+                    let half = T::cast_from(1) / T::cast_from(2);
+                    let center = f((
+                        us[i] + (us[i + 1] - us[i]) * half,
+                        vs[j] + (vs[j + 1] - vs[j]) * half,
+                    ));
+                    if (center >= T::zero()) == corners[0] {
+                        segments.push((crossed[0], crossed[1]));
+                        segments.push((crossed[2], crossed[3]));
+                    } else {
+                        segments.push((crossed[3], crossed[0]));
+                        segments.push((crossed[1], crossed[2]));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    chain(&segments)
+        .into_iter()
+        .map(|(edges, closed)| {
+            let uv: Vec<_> = edges.iter().map(|e| crossings[e]).collect();
+            Contour {
+                points: uv.iter().map(|&uv| surface.at(uv)).collect(),
+                uv,
+                closed,
+            }
+        })
+        .collect()
+}
+
+/// Evenly spaced samples covering the whole range at most `step` apart.
+fn samples<T: Real>(range: RangeInclusive<T>, step: T) -> Vec<T> {
+    assert!(step > T::zero(), "step must be positive");
+    let (start, end) = range.into_inner();
+    let count: usize = ((end - start) / step).ceil().cast();
+    let count = count.max(1);
+    (0..=count)
+        .map(|i| start + (end - start) * T::cast_from(i) / T::cast_from(count))
+        .collect()
+}
+
+/// Locate the zero of `f` between `a` and `b` which have values of opposite sign.
+fn refine<T: Real>(f: impl Fn(UV<T>) -> T, (a, fa): (UV<T>, T), (b, fb): (UV<T>, T)) -> UV<T> {
+    let at = |t: T| (a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t);
+
+    // illinois variant of regula falsi
+    let (mut t0, mut f0, mut t1, mut f1) = (T::zero(), fa, T::cast_from(1), fb);
+    let mut t = t0;
+    let mut side = 0;
+    for _ in 0..64 {
+        if f1 == f0 {
+            break;
+        }
+        t = (t0 * f1 - t1 * f0) / (f1 - f0);
+        let ft = f(at(t));
+        if ft == T::zero() || (t1 - t0).abs() <= T::default_epsilon() {
+            break;
+        }
+        if (ft >= T::zero()) == (f1 >= T::zero()) {
+            t1 = t;
+            f1 = ft;
+            if side == -1 {
+                f0 /= T::cast_from(2);
+            }
+            side = -1;
+        } else {
+            t0 = t;
+            f0 = ft;
+            if side == 1 {
+                f1 /= T::cast_from(2);
+            }
+            side = 1;
+        }
+    }
+    at(t)
+}
+
+/// Join segments sharing edges into polylines, open ones first.
+fn chain(segments: &[(Edge, Edge)]) -> Vec<(Vec<Edge>, bool)> {
+    let mut neighbours: BTreeMap<Edge, Vec<usize>> = BTreeMap::new();
+    for (s, &(a, b)) in segments.iter().enumerate() {
+        neighbours.entry(a).or_default().push(s);
+        neighbours.entry(b).or_default().push(s);
+    }
+
+    let mut used = alloc::vec![false; segments.len()];
+    let mut chains = Vec::new();
+    let ends: Vec<_> = neighbours
+        .iter()
+        .filter(|(_, s)| s.len() == 1)
+        .map(|(e, _)| *e)
+        .collect();
+    let starts = ends.into_iter().chain(segments.iter().map(|s| s.0));
+
+    for start in starts {
+        let mut edges = Vec::from([start]);
+        let mut current = start;
+        while let Some(&s) = neighbours[&current].iter().find(|&&s| !used[s]) {
+            used[s] = true;
+            let (a, b) = segments[s];
+            current = if a == current { b } else { a };
+            edges.push(current);
+        }
+
+        if edges.len() > 1 {
+            let closed = edges.len() > 2 && edges[0] == edges[edges.len() - 1];
+            if closed {
+                edges.pop();
+            }
+            chains.push((edges, closed));
+        }
+    }
+    chains
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::control_points::ControlGrid;
+    use crate::splines::Spline;
+    use crate::surfaces::BSurface;
+    use alloc::vec::Vec;
+    use nalgebra::Vector3;
+
+    fn tube() -> BSurface<nalgebra::Const<3>, f64> {
+        let ring = [(-1., -1.), (1., -1.), (1., 1.), (-1., 1.)];
+        let points = (0..4)
+            .flat_map(|z| ring.map(|(x, y)| Vector3::new(x, y, z as f64)))
+            .collect();
+        let mut grid = ControlGrid::new(2, 4, points);
+        grid.set_u_wrapping(true);
+        BSurface::new(grid)
+    }
+
+    #[test]
+    fn it_finds_closed_contours() {
+        let contours = tube().intersect_plane(Vector3::new(0., 0., 1.5), Vector3::z(), 0.1);
+
+        assert_eq!(1, contours.len());
+        assert!(contours[0].is_closed());
+        for p in contours[0].points() {
+            assert!((p.z - 1.5).abs() < 1e-9);
+        }
+
+        let (spline, error) = contours[0].spline(1e-3).unwrap();
+        assert!(error <= 1e-3);
+        assert!(spline.control_points().len() < contours[0].points().len());
+        let (start, end) = spline.range().into_inner();
+        assert!((spline.at(start) - spline.at(end)).norm() < 1e-12);
+        for i in 0..=100 {
+            let p = spline.at(start + (end - start) * i as f64 / 100.);
+            assert!((p.z - 1.5).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "step must be positive")]
+    fn it_rejects_empty_steps() {
+        tube().intersect_plane(Vector3::zeros(), Vector3::x(), 0.);
+    }
+
+    #[test]
+    fn it_finds_open_contours() {
+        let contours = tube().intersect_plane(Vector3::zeros(), Vector3::x(), 0.1);
+
+        assert_eq!(2, contours.len());
+        for contour in contours {
+            assert!(!contour.is_closed());
+            let xs: Vec<_> = contour.points().iter().map(|p| p.x.abs()).collect();
+            assert!(xs.iter().all(|&x| x < 1e-9));
+        }
+    }
+}
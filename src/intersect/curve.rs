@@ -0,0 +1,144 @@
+use crate::bezier::roots;
+use crate::splines::{BSpline, NURBSpline};
+use crate::types::Real;
+use alloc::vec::Vec;
+use nalgebra::allocator::Allocator;
+use nalgebra::{Const, DefaultAllocator, Dim, Vector2, Vector3};
+
+impl<T: Real> BSpline<Const<3>, T> {
+    /// Parameters where the curve meets the plane through `origin` with `normal`, in ascending order.
+    pub fn intersect_plane(&self, origin: Vector3<T>, normal: Vector3<T>) -> Vec<T> {
+        check_plane(self, &origin, &normal);
+        roots(self.bezier_segments(|p| (p - origin).dot(&normal)))
+    }
+}
+
+impl<T: Real> BSpline<Const<2>, T> {
+    /// Parameters where the curve meets the line through `point` along `direction`, in ascending order.
+    pub fn intersect_line(&self, point: Vector2<T>, direction: Vector2<T>) -> Vec<T> {
+        check_line(self, &point, &direction);
+        roots(self.bezier_segments(|p| (p - point).perp(&direction)))
+    }
+}
+
+impl<T: Real> NURBSpline<'_, Const<4>, T> {
+    /// Parameters where the curve meets the plane through `origin` with `normal`, in ascending order.
+    pub fn intersect_plane(&self, origin: Vector3<T>, normal: Vector3<T>) -> Vec<T> {
+        check_plane(self.spline(), &origin, &normal);
+        roots(
+            self.spline()
+                .bezier_segments(|p| (p.xyz() - origin).dot(&normal) * p.w),
+        )
+    }
+}
+
+impl<T: Real> NURBSpline<'_, Const<3>, T> {
+    /// Parameters where the curve meets the line through `point` along `direction`, in ascending order.
+    pub fn intersect_line(&self, point: Vector2<T>, direction: Vector2<T>) -> Vec<T> {
+        check_line(self.spline(), &point, &direction);
+        roots(
+            self.spline()
+                .bezier_segments(|p| (p.xy() - point).perp(&direction) * p.z),
+        )
+    }
+}
+
+fn check_plane<D: Dim, T: Real>(spline: &BSpline<D, T>, origin: &Vector3<T>, normal: &Vector3<T>)
+where
+    DefaultAllocator: Allocator<T, D>,
+{
+    assert!(
+        origin.iter().chain(normal.iter()).all(|x| x.is_finite()),
+        "origin and normal must be finite"
+    );
+    check_points(spline);
+}
+
+fn check_line<D: Dim, T: Real>(spline: &BSpline<D, T>, point: &Vector2<T>, direction: &Vector2<T>)
+where
+    DefaultAllocator: Allocator<T, D>,
+{
+    assert!(
+        point.iter().chain(direction.iter()).all(|x| x.is_finite()),
+        "point and direction must be finite"
+    );
+    check_points(spline);
+}
+
+fn check_points<D: Dim, T: Real>(spline: &BSpline<D, T>)
+where
+    DefaultAllocator: Allocator<T, D>,
+{
+    assert!(
+        spline
+            .control_points()
+            .iter()
+            .all(|p| p.iter().all(|x| x.is_finite())),
+        "control points must be finite"
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::control_points::ControlVec;
+    use crate::splines::{BSpline, Spline};
+    use alloc::vec;
+    use nalgebra::{Vector2, Vector3};
+
+    #[test]
+    fn it_intersects_planes() {
+        let spline = BSpline::new(ControlVec::new(
+            3,
+            vec![
+                Vector3::new(0f64, 0., -1.),
+                Vector3::new(1., 0., 2.),
+                Vector3::new(2., 0., -2.),
+                Vector3::new(3., 0., 2.),
+                Vector3::new(4., 0., -1.),
+            ],
+        ));
+
+        let hits = spline.intersect_plane(Vector3::zeros(), Vector3::z());
+
+        assert_eq!(2, hits.len());
+        for u in hits {
+            assert!(spline.at(u).z.abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn it_intersects_circles_with_lines() {
+        let circle = BSpline::circle();
+
+        let hits = circle
+            .nurbs()
+            .intersect_line(Vector2::zeros(), Vector2::x());
+
+        assert_eq!(2, hits.len());
+        for u in hits {
+            let point = circle.nurbs().at(u);
+            assert!(point.y.abs() < 1e-9);
+            assert!((point.x.abs() - 0.5).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "origin and normal must be finite")]
+    fn it_rejects_planes_which_are_not_finite() {
+        let line = BSpline::new(ControlVec::new(
+            1,
+            vec![Vector3::new(0f64, 0., -1.), Vector3::new(0., 0., 1.)],
+        ));
+        line.intersect_plane(Vector3::new(0., f64::NAN, 0.), Vector3::z());
+    }
+
+    #[test]
+    #[should_panic(expected = "control points must be finite")]
+    fn it_rejects_points_which_are_not_finite() {
+        let line = BSpline::new(ControlVec::new(
+            1,
+            vec![Vector2::new(0f64, f64::INFINITY), Vector2::new(0., 1.)],
+        ));
+        line.intersect_line(Vector2::zeros(), Vector2::x());
+    }
+}
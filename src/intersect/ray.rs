@@ -0,0 +1,266 @@
+use crate::bezier::BezierPatch;
+use crate::rational::{bounding_box, Patches};
+use crate::surfaces::{BSurface, NURBSurface, UV};
+use crate::types::Real;
+use nalgebra::{Const, Matrix3, Vector3, Vector4};
+
+/// Patches smaller than this fraction of the surface are solved directly.
+const FINE: usize = 32;
+/// Maximum number of subdivisions of a patch.
+const MAX_DEPTH: usize = 16;
+
+/// Where a ray hits a surface.
+#[derive(Debug, Clone)]
+pub struct RayHit<T> {
+    distance: T,
+    uv: UV<T>,
+    point: Vector3<T>,
+    normal: Vector3<T>,
+}
+
+impl<T: Real> RayHit<T> {
+    /// Distance from the origin of the ray.
+    pub fn distance(&self) -> T {
+        self.distance
+    }
+
+    /// Coordinate of the hit on the surface.
+    pub fn uv(&self) -> UV<T> {
+        self.uv
+    }
+
+    /// Point of the hit.
+    pub fn point(&self) -> Vector3<T> {
+        self.point
+    }
+
+    /// Unit normal of the surface at the hit, following the orientation of the surface.
+    pub fn normal(&self) -> Vector3<T> {
+        self.normal
+    }
+}
+
+impl<T: Real> BSurface<Const<3>, T> {
+    /// Nearest hit of the ray from `origin` along `direction`, or `None` if there is none
+    /// or the ray is not finite or has no direction.
+    pub fn intersect_ray(&self, origin: Vector3<T>, direction: Vector3<T>) -> Option<RayHit<T>> {
+        intersect(&Patches::new(self), origin, direction)
+    }
+}
+
+impl<T: Real> NURBSurface<'_, Const<4>, T> {
+    /// Nearest hit of the ray from `origin` along `direction`, or `None` if there is none
+    /// or the ray is not finite or has no direction.
+    pub fn intersect_ray(&self, origin: Vector3<T>, direction: Vector3<T>) -> Option<RayHit<T>> {
+        intersect(&Patches::new(self), origin, direction)
+    }
+}
+
+/// Ray from an origin along a unit direction.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Ray<T> {
+    pub origin: Vector3<T>,
+    pub direction: Vector3<T>,
+}
+
+impl<T: Real> Ray<T> {
+    /// Ray from `origin` along `direction`, or `None` if either is not finite or the
+    /// direction vanishes.
+    pub fn new(origin: Vector3<T>, direction: Vector3<T>) -> Option<Self> {
+        if !origin.iter().chain(direction.iter()).all(|x| x.is_finite()) {
+            return None;
+        }
+        let direction = direction.try_normalize(T::zero())?;
+        Some(Self { origin, direction })
+    }
+}
+
+fn intersect<T: Real>(
+    surface: &Patches<T>,
+    origin: Vector3<T>,
+    direction: Vector3<T>,
+) -> Option<RayHit<T>> {
+    let ray = Ray::new(origin, direction)?;
+    let size = surface_size(surface);
+    let mut nearest: Option<RayHit<T>> = None;
+    for patch in surface.patches() {
+        let limit = nearest.as_ref().map(|h| h.distance);
+        if let Some(hit) = patch_hit(surface, patch, &ray, limit, size) {
+            nearest = Some(hit);
+        }
+    }
+    nearest
+}
+
+/// Diagonal of the box around the whole surface.
+pub(crate) fn surface_size<T: Real>(surface: &Patches<T>) -> T {
+    surface
+        .patches()
+        .iter()
+        .map(|p| {
+            let b = bounding_box(p);
+            (b.max() - b.min()).norm()
+        })
+        .fold(T::zero(), |a, b| a.max(b))
+}
+
+/// Nearest hit of `ray` within `patch` closer than `limit`, subdividing the patch
+/// until it is small enough for Newton's method to converge. Patches that small are
+/// not split further, whether Newton's method finds a hit in them or not.
+pub(crate) fn patch_hit<T: Real>(
+    surface: &Patches<T>,
+    patch: &BezierPatch<T, Vector4<T>>,
+    ray: &Ray<T>,
+    limit: Option<T>,
+    size: T,
+) -> Option<RayHit<T>> {
+    let mut stack = alloc::vec![(patch.clone(), 0)];
+    let mut nearest: Option<RayHit<T>> = None;
+    let fine = size / T::cast_from(FINE);
+
+    while let Some((patch, depth)) = stack.pop() {
+        let bbox = bounding_box(&patch);
+        let Some(entry) = bbox.ray_entry(&ray.origin, &ray.direction) else {
+            continue;
+        };
+        let limit = nearest.as_ref().map(|h| h.distance).or(limit);
+        if limit.is_some_and(|limit| entry > limit) {
+            continue;
+        }
+
+        if (bbox.max() - bbox.min()).norm() <= fine || depth >= MAX_DEPTH {
+            if let Some(hit) = newton(surface, &patch, ray, size) {
+                if limit.is_none_or(|limit| hit.distance < limit) {
+                    nearest = Some(hit);
+                }
+            }
+            continue;
+        }
+        for quarter in patch.quarters() {
+            stack.push((quarter, depth + 1));
+        }
+    }
+    nearest
+}
+
+/// Solve for the hit of `ray` inside `patch` starting from its center.
+fn newton<T: Real>(
+    surface: &Patches<T>,
+    patch: &BezierPatch<T, Vector4<T>>,
+    ray: &Ray<T>,
+    size: T,
+) -> Option<RayHit<T>> {
+    let tolerance = size * T::default_epsilon().sqrt();
+    let slack = T::default_epsilon().sqrt();
+    let half = T::cast_from(1) / T::cast_from(2);
+
+    let (mut u, mut v) = patch.parameter((half, half));
+    let mut t = (surface.at((u, v)) - ray.origin).dot(&ray.direction);
+    for _ in 0..16 {
+        let (point, du, dv) = surface.derivatives(surface.clamp((u, v)));
+        let f = point - (ray.origin + ray.direction * t);
+        let (s, r) = patch.local((u, v));
+        let inside = |x: T| x >= -slack && x <= T::cast_from(1) + slack;
+        if f.norm() <= tolerance {
+            let normal = du.cross(&dv);
+            return (inside(s) && inside(r) && t >= -tolerance).then(|| RayHit {
+                distance: t,
+                uv: surface.clamp((u, v)),
+                point,
+                normal: normal.normalize(),
+            });
+        }
+        if s < -T::cast_from(1)
+            || s > T::cast_from(2)
+            || r < -T::cast_from(1)
+            || r > T::cast_from(2)
+        {
+            return None;
+        }
+
+        let delta = Matrix3::from_columns(&[du, dv, -ray.direction])
+            .lu()
+            .solve(&f)?;
+        u -= delta.x;
+        v -= delta.y;
+        t -= delta.z;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::control_points::ControlGrid;
+    use crate::surfaces::{BSurface, Surface};
+    use alloc::vec;
+    use nalgebra::Vector3;
+
+    #[test]
+    fn it_hits_planes() {
+        let plane = plane();
+        let hit = plane
+            .intersect_ray(Vector3::new(0.2, 0.3, 5.), Vector3::new(0., 0., -2.))
+            .unwrap();
+
+        assert!((hit.distance() - 5.).abs() < 1e-9);
+        assert!((hit.point() - Vector3::new(0.2, 0.3, 0.)).norm() < 1e-9);
+        assert!((plane.at(hit.uv()) - hit.point()).norm() < 1e-9);
+        assert!((hit.normal().z.abs() - 1.).abs() < 1e-9);
+
+        assert!(plane
+            .intersect_ray(Vector3::new(0.2, 0.3, 5.), Vector3::z())
+            .is_none());
+    }
+
+    #[test]
+    fn it_hits_the_nearest_side() {
+        let ring = [(-1f64, -1.), (1., -1.), (1., 1.), (-1., 1.)];
+        let points = (0..4)
+            .flat_map(|z| ring.map(|(x, y)| Vector3::new(x, y, z as f64)))
+            .collect();
+        let mut grid = ControlGrid::new(2, 4, points);
+        grid.set_u_wrapping(true);
+        let tube = BSurface::new(grid);
+
+        let origin = Vector3::new(-5., 0.1, 1.5);
+        let hit = tube.intersect_ray(origin, Vector3::x()).unwrap();
+
+        assert!(hit.point().x < 0.);
+        assert!((hit.point() - (origin + Vector3::x() * hit.distance())).norm() < 1e-9);
+        assert!((tube.at(hit.uv()) - hit.point()).norm() < 1e-9);
+    }
+
+    fn plane() -> BSurface<nalgebra::Const<3>, f64> {
+        let mut plane = BSurface::new(ControlGrid::new(
+            1,
+            2,
+            vec![
+                Vector3::new(-1f64, -1., 0.),
+                Vector3::new(1., -1., 0.),
+                Vector3::new(-1., 1., 0.),
+                Vector3::new(1., 1., 0.),
+            ],
+        ));
+        plane.u_knots_mut().clamp_ends();
+        plane.v_knots_mut().clamp_ends();
+        plane
+    }
+
+    #[test]
+    fn it_rejects_rays_without_direction() {
+        let plane = plane();
+        assert!(plane
+            .intersect_ray(Vector3::new(0.2, 0.3, 5.), Vector3::zeros())
+            .is_none());
+        assert!(plane
+            .intersect_ray(Vector3::new(0.2, f64::NAN, 5.), -Vector3::z())
+            .is_none());
+    }
+
+    #[test]
+    fn it_stops_splitting_patches_in_the_plane_of_the_ray() {
+        // the ray lies in the plane, so Newton's method has nothing to converge to
+        let hit = plane().intersect_ray(Vector3::new(-2., 0.1, 0.), Vector3::x());
+        assert!(hit.is_none());
+    }
+}
@@ -0,0 +1,500 @@
+use crate::algorithms::distance_to_segment;
+use crate::export::BoundingBox;
+use crate::intersect::fit;
+use crate::rational::{bounding_box, Patches, RationalSurface};
+use crate::splines::BSpline;
+use crate::surfaces::{BSurface, NURBSurface, UV};
+use crate::types::Real;
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use nalgebra::{Const, Matrix2, Matrix3x4, Matrix4, Vector2, Vector3, Vector4};
+
+/// Maximum number of points traced along a single curve.
+const MAX_STEPS: usize = 1 << 16;
+/// Maximum number of subdivisions while searching for starting points.
+const MAX_DEPTH: usize = 24;
+
+/// Curve where two surfaces meet.
+#[derive(Debug, Clone)]
+pub struct SurfaceIntersection<T> {
+    uv: Vec<UV<T>>,
+    other_uv: Vec<UV<T>>,
+    points: Vec<Vector3<T>>,
+    closed: bool,
+}
+
+impl<T: Real> SurfaceIntersection<T> {
+    /// Points of the curve in the parameter space of the intersected surface.
+    pub fn uv(&self) -> &[UV<T>] {
+        &self.uv
+    }
+
+    /// Points of the curve in the parameter space of the other surface.
+    pub fn other_uv(&self) -> &[UV<T>] {
+        &self.other_uv
+    }
+
+    /// Points of the curve in 3D.
+    pub fn points(&self) -> &[Vector3<T>] {
+        &self.points
+    }
+
+    /// If the curve forms a loop. The last point then connects back to the first.
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+
+    /// Cubic BSpline passing within `tolerance` of every point of the curve, starting and
+    /// ending at the first point of a closed one. Returned with the largest distance from a
+    /// point to the spline, or `None` if the fit cannot be solved.
+    pub fn spline(&self, tolerance: T) -> Option<(BSpline<Const<3>, T>, T)> {
+        fit(&self.points, self.closed, tolerance)
+    }
+}
+
+impl<T: Real> BSurface<Const<3>, T> {
+    /// Curves where the surface meets `other`, a 3D `BSurface` or 4D `NURBSurface`.
+    /// `step` is the distance between traced points.
+    pub fn intersect_surface(
+        &self,
+        other: &impl RationalSurface<T>,
+        step: T,
+    ) -> Vec<SurfaceIntersection<T>> {
+        intersect(&Patches::new(self), &Patches::new(other), step)
+    }
+}
+
+impl<T: Real> NURBSurface<'_, Const<4>, T> {
+    /// Curves where the surface meets `other`, a 3D `BSurface` or 4D `NURBSurface`.
+    /// `step` is the distance between traced points.
+    pub fn intersect_surface(
+        &self,
+        other: &impl RationalSurface<T>,
+        step: T,
+    ) -> Vec<SurfaceIntersection<T>> {
+        intersect(&Patches::new(self), &Patches::new(other), step)
+    }
+}
+
+/// Position on both surfaces, `(u, v)` of the first followed by `(u, v)` of the second.
+type State<T> = Vector4<T>;
+
+/// Additional equation pinning down a single solution while correcting.
+#[derive(Debug, Clone, Copy)]
+enum Constraint<T> {
+    /// Distance along `tangent` from `point`.
+    Distance(Vector3<T>, Vector3<T>, T),
+    /// Parameter at `index` of the state has `value`.
+    Fixed(usize, T),
+}
+
+fn intersect<T: Real>(a: &Patches<T>, b: &Patches<T>, step: T) -> Vec<SurfaceIntersection<T>> {
+    let tolerance = step * T::default_epsilon().sqrt();
+
+    let mut curves: Vec<SurfaceIntersection<T>> = Vec::new();
+    let mut traced = SegmentIndex::new(a, step);
+    for seed in seeds(a, b, step, tolerance) {
+        if traced.near(&a.at(first(seed)), step / T::cast_from(4)) {
+            continue;
+        }
+
+        let (forward, closed) = march(a, b, seed, step, tolerance, T::cast_from(1));
+        let states: Vec<_> = if closed {
+            [seed].into_iter().chain(forward).collect()
+        } else {
+            let (backward, _) = march(a, b, seed, step, tolerance, -T::cast_from(1));
+            let backward = backward.into_iter().rev();
+            backward.chain([seed]).chain(forward).collect()
+        };
+
+        let points: Vec<_> = states.iter().map(|&x| a.at(first(x))).collect();
+        for w in points.windows(2) {
+            traced.insert(w[0], w[1]);
+        }
+        if closed {
+            traced.insert(points[points.len() - 1], points[0]);
+        }
+        curves.push(SurfaceIntersection {
+            uv: states.iter().map(|&x| first(x)).collect(),
+            other_uv: states.iter().map(|&x| second(x)).collect(),
+            points,
+            closed,
+        });
+    }
+    curves
+}
+
+/// Starting points on every curve found by subdividing overlapping patches.
+fn seeds<T: Real>(a: &Patches<T>, b: &Patches<T>, step: T, tolerance: T) -> Vec<State<T>> {
+    let mut pairs = Vec::new();
+    for pa in a.patches() {
+        for pb in b.patches() {
+            pairs.push((pa.clone(), pb.clone(), 0));
+        }
+    }
+
+    let half = T::cast_from(1) / T::cast_from(2);
+    let mut seeds = Vec::new();
+    while let Some((pa, pb, depth)) = pairs.pop() {
+        let (box_a, box_b) = (bounding_box(&pa), bounding_box(&pb));
+        if !box_a.intersects(&box_b) {
+            continue;
+        }
+
+        let size_a = (box_a.max() - box_a.min()).norm();
+        let size_b = (box_b.max() - box_b.min()).norm();
+        if size_a.max(size_b) <= step || depth >= MAX_DEPTH {
+            let center = pa.parameter((half, half));
+            let other = pb.parameter((half, half));
+            let guess = Vector4::new(center.0, center.1, other.0, other.1);
+            if let Some(seed) = converge(a, b, guess, tolerance) {
+                seeds.push(seed);
+            }
+        } else if size_a >= size_b {
+            for quarter in pa.quarters() {
+                pairs.push((quarter, pb.clone(), depth + 1));
+            }
+        } else {
+            for quarter in pb.quarters() {
+                pairs.push((pa.clone(), quarter, depth + 1));
+            }
+        }
+    }
+    seeds
+}
+
+/// Move `guess` onto the intersection using minimum norm Newton steps.
+fn converge<T: Real>(
+    a: &Patches<T>,
+    b: &Patches<T>,
+    mut guess: State<T>,
+    tolerance: T,
+) -> Option<State<T>> {
+    for _ in 0..32 {
+        let (pa, au, av) = a.derivatives(first(guess));
+        let (pb, bu, bv) = b.derivatives(second(guess));
+        let f = pa - pb;
+        if f.norm() <= tolerance {
+            return Some(guess);
+        }
+
+        let j = Matrix3x4::from_columns(&[au, av, -bu, -bv]);
+        let inverse = (j * j.transpose()).try_inverse()?;
+        guess = clamp(a, b, guess - j.transpose() * (inverse * f));
+    }
+    None
+}
+
+/// Trace the curve through `seed` in `direction` until it ends or returns to `seed`.
+fn march<T: Real>(
+    a: &Patches<T>,
+    b: &Patches<T>,
+    seed: State<T>,
+    step: T,
+    tolerance: T,
+    direction: T,
+) -> (Vec<State<T>>, bool) {
+    let start = a.at(first(seed));
+    let mut states = Vec::new();
+    let mut state = seed;
+    let mut previous: Option<Vector3<T>> = None;
+
+    for _ in 0..MAX_STEPS {
+        let Some(tangent) = tangent(a, b, state) else {
+            break;
+        };
+        // keep heading the same way along the curve
+        let tangent = match previous {
+            Some(p) if p.dot(&tangent) < T::zero() => -tangent,
+            Some(_) => tangent,
+            None => tangent * direction,
+        };
+        previous = Some(tangent);
+
+        let point = a.at(first(state));
+        let mut h = step;
+        let mut next = None;
+        for _ in 0..4 {
+            next = advance(a, b, state, point, tangent, h, tolerance);
+            if next.is_some() {
+                break;
+            }
+            h /= T::cast_from(2);
+        }
+        let Some((next, boundary)) = next else {
+            break;
+        };
+
+        if states.len() > 1
+            && distance_to_segment(&start, &point, &a.at(first(next))) <= step / T::cast_from(2)
+        {
+            return (states, true);
+        }
+        states.push(next);
+        if boundary {
+            break;
+        }
+        state = next;
+    }
+    (states, false)
+}
+
+/// Step from `state` at `point` a distance `h` along `tangent`, returning if a boundary was hit.
+fn advance<T: Real>(
+    a: &Patches<T>,
+    b: &Patches<T>,
+    state: State<T>,
+    point: Vector3<T>,
+    tangent: Vector3<T>,
+    h: T,
+    tolerance: T,
+) -> Option<(State<T>, bool)> {
+    let delta = tangent * h;
+    let du_a = parameter_step(a, first(state), delta)?;
+    let du_b = parameter_step(b, second(state), delta)?;
+    let change = Vector4::new(du_a.x, du_a.y, du_b.x, du_b.y);
+
+    // stop at the first boundary of a direction which does not wrap
+    let ranges = [a.u_range(), a.v_range(), b.u_range(), b.v_range()];
+    let wrapping = [
+        a.wrapping().0,
+        a.wrapping().1,
+        b.wrapping().0,
+        b.wrapping().1,
+    ];
+    let mut fraction = T::cast_from(1);
+    let mut fixed = None;
+    for k in 0..4 {
+        let target = state[k] + change[k];
+        let bound = if target > *ranges[k].end() {
+            *ranges[k].end()
+        } else if target < *ranges[k].start() {
+            *ranges[k].start()
+        } else {
+            continue;
+        };
+        let f = (bound - state[k]) / change[k];
+        if !wrapping[k] && f < fraction {
+            fraction = f;
+            fixed = Some((k, bound));
+        }
+    }
+    if fraction <= T::default_epsilon() {
+        return None;
+    }
+
+    let constraint = match fixed {
+        Some((k, bound)) => Constraint::Fixed(k, bound),
+        None => Constraint::Distance(point, tangent, h),
+    };
+    let next = correct(a, b, state + change * fraction, constraint, tolerance)?;
+    let moved = (a.at(first(next)) - point).dot(&tangent);
+    (moved > T::zero()).then_some((next, fixed.is_some()))
+}
+
+/// Move `guess` onto the intersection while satisfying `constraint` using Newton's method.
+fn correct<T: Real>(
+    a: &Patches<T>,
+    b: &Patches<T>,
+    mut guess: State<T>,
+    constraint: Constraint<T>,
+    tolerance: T,
+) -> Option<State<T>> {
+    for _ in 0..16 {
+        let (pa, au, av) = a.derivatives(first(guess));
+        let (pb, bu, bv) = b.derivatives(second(guess));
+        let f = pa - pb;
+
+        let (row, value) = match constraint {
+            Constraint::Distance(point, tangent, h) => (
+                Vector4::new(au.dot(&tangent), av.dot(&tangent), T::zero(), T::zero()),
+                (pa - point).dot(&tangent) - h,
+            ),
+            Constraint::Fixed(k, v) => (Vector4::ith(k, T::cast_from(1)), guess[k] - v),
+        };
+        if f.norm() <= tolerance && value.abs() <= tolerance {
+            return Some(guess);
+        }
+
+        let mut j = Matrix4::zeros();
+        j.fixed_view_mut::<3, 4>(0, 0)
+            .copy_from(&Matrix3x4::from_columns(&[au, av, -bu, -bv]));
+        j.set_row(3, &row.transpose());
+        let delta = j.lu().solve(&f.push(value))?;
+        guess = clamp(a, b, guess - delta);
+    }
+    None
+}
+
+/// Unit direction of the curve at `state` or `None` where the surfaces touch tangentially.
+fn tangent<T: Real>(a: &Patches<T>, b: &Patches<T>, state: State<T>) -> Option<Vector3<T>> {
+    let na = a.normal(first(state));
+    let nb = b.normal(second(state));
+    let t = na.cross(&nb);
+    let norm = t.norm();
+    (norm > na.norm() * nb.norm() * T::default_epsilon().sqrt()).then(|| t / norm)
+}
+
+/// Change in `uv` which best moves the surface point by `delta`.
+fn parameter_step<T: Real>(
+    surface: &Patches<T>,
+    uv: UV<T>,
+    delta: Vector3<T>,
+) -> Option<Vector2<T>> {
+    let (_, du, dv) = surface.derivatives(uv);
+    let normal = Matrix2::new(du.dot(&du), du.dot(&dv), du.dot(&dv), dv.dot(&dv));
+    Some(normal.try_inverse()? * Vector2::new(du.dot(&delta), dv.dot(&delta)))
+}
+
+fn clamp<T: Real>(a: &Patches<T>, b: &Patches<T>, state: State<T>) -> State<T> {
+    let (u, v) = a.clamp(first(state));
+    let (s, t) = b.clamp(second(state));
+    Vector4::new(u, v, s, t)
+}
+
+fn first<T: Real>(state: State<T>) -> UV<T> {
+    (state.x, state.y)
+}
+
+fn second<T: Real>(state: State<T>) -> UV<T> {
+    (state.z, state.w)
+}
+
+/// Line segment between two points.
+type Segment<T> = (Vector3<T>, Vector3<T>);
+
+/// Segments of the traced curves bucketed into cubic cells, for finding those near a point.
+struct SegmentIndex<T> {
+    origin: Vector3<T>,
+    size: T,
+    cells: BTreeMap<[usize; 3], Vec<Segment<T>>>,
+}
+
+impl<T: Real> SegmentIndex<T> {
+    /// Empty index of segments on `surface` in cells of `size`.
+    fn new(surface: &Patches<T>, size: T) -> Self {
+        let origin = surface
+            .patches()
+            .iter()
+            .map(bounding_box)
+            .reduce(|a, b| a.union(&b))
+            .map_or(Vector3::zeros(), |b| b.min() - Vector3::repeat(size));
+        Self {
+            origin,
+            size,
+            cells: BTreeMap::new(),
+        }
+    }
+
+    fn cell(&self, point: &Vector3<T>) -> [usize; 3] {
+        let offset = (point - self.origin) / self.size;
+        [0, 1, 2].map(|i| offset[i].floor().max(T::zero()).cast())
+    }
+
+    /// Add the segment from `a` to `b` to every cell its bounding box covers.
+    fn insert(&mut self, a: Vector3<T>, b: Vector3<T>) {
+        let bbox = BoundingBox::new([&a, &b]);
+        let (min, max) = (self.cell(bbox.min()), self.cell(bbox.max()));
+        for x in min[0]..=max[0] {
+            for y in min[1]..=max[1] {
+                for z in min[2]..=max[2] {
+                    self.cells.entry([x, y, z]).or_default().push((a, b));
+                }
+            }
+        }
+    }
+
+    /// If a segment is within `distance` of `point`, which is at most the cell size.
+    fn near(&self, point: &Vector3<T>, distance: T) -> bool {
+        let [x, y, z] = self.cell(point);
+        let around = |c: usize| c.saturating_sub(1)..=c + 1;
+        around(x).any(|x| {
+            around(y).any(|y| {
+                around(z).any(|z| {
+                    self.cells.get(&[x, y, z]).is_some_and(|segments| {
+                        segments
+                            .iter()
+                            .any(|&(a, b)| distance_to_segment(point, &a, &b) <= distance)
+                    })
+                })
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::control_points::ControlGrid;
+    use crate::splines::Spline;
+    use crate::surfaces::{BSurface, Surface};
+    use alloc::vec::Vec;
+    use nalgebra::{Const, Vector3};
+
+    fn plane(points: [Vector3<f64>; 4]) -> BSurface<Const<3>, f64> {
+        let mut surface = BSurface::new(ControlGrid::new(1, 2, Vec::from(points)));
+        surface.u_knots_mut().clamp_ends();
+        surface.v_knots_mut().clamp_ends();
+        surface
+    }
+
+    #[test]
+    fn it_intersects_crossing_planes() {
+        let floor = plane([
+            Vector3::new(-1., -1., 0.),
+            Vector3::new(1., -1., 0.),
+            Vector3::new(-1., 1., 0.),
+            Vector3::new(1., 1., 0.),
+        ]);
+        let wall = plane([
+            Vector3::new(0., -2., -1.),
+            Vector3::new(0., 2., -1.),
+            Vector3::new(0., -2., 1.),
+            Vector3::new(0., 2., 1.),
+        ]);
+
+        let curves = floor.intersect_surface(&wall, 0.1);
+
+        assert_eq!(1, curves.len());
+        let curve = &curves[0];
+        assert!(!curve.is_closed());
+        assert!(curve.points().len() > 10);
+        for (point, (uv, other)) in curve
+            .points()
+            .iter()
+            .zip(curve.uv().iter().zip(curve.other_uv()))
+        {
+            assert!(point.x.abs() < 1e-6 && point.z.abs() < 1e-6);
+            assert!((floor.at(*uv) - wall.at(*other)).norm() < 1e-6);
+        }
+        let ys: Vec<_> = curve.points().iter().map(|p| p.y).collect();
+        assert!(ys.iter().any(|&y| (y.abs() - 1.).abs() < 1e-6));
+    }
+
+    #[test]
+    fn it_finds_closed_curves() {
+        let ring = [(-1., -1.), (1., -1.), (1., 1.), (-1., 1.)];
+        let points = (0..4)
+            .flat_map(|z| ring.map(|(x, y)| Vector3::new(x, y, z as f64)))
+            .collect();
+        let mut grid = ControlGrid::new(2, 4, points);
+        grid.set_u_wrapping(true);
+        let tube = BSurface::new(grid);
+        let cut = plane([
+            Vector3::new(-2., -2., 1.),
+            Vector3::new(2., -2., 1.5),
+            Vector3::new(-2., 2., 1.),
+            Vector3::new(2., 2., 1.5),
+        ]);
+
+        let curves = tube.intersect_surface(&cut, 0.05);
+
+        assert_eq!(1, curves.len());
+        assert!(curves[0].is_closed());
+
+        let (spline, error) = curves[0].spline(1e-4).unwrap();
+        assert!(error <= 1e-4);
+        assert!(spline.control_points().len() < curves[0].points().len());
+        let (start, end) = spline.range().into_inner();
+        assert!((spline.at(start) - spline.at(end)).norm() < 1e-12);
+    }
+}
@@ -1,22 +1,23 @@
+use crate::types::Scalar;
 use alloc::vec::Vec;
 use core::fmt::Debug;
 use core::marker::PhantomData;
 use core::ops::{Index, IndexMut, RangeInclusive};
 
-/// A knot vector.
+/// A knot vector of scalar knots, which need not be whole numbers.
 #[derive(Debug, Clone)]
-pub struct Knots<'a, T = &'a [usize]> {
+pub struct Knots<'a, T, S = &'a [T]> {
     degree: usize,
-    knot_vec: T,
-    _phantom: PhantomData<&'a ()>,
+    knot_vec: S,
+    _phantom: PhantomData<&'a T>,
 }
 
 /// A mutable knot vector.
-pub type KnotsMut<'a> = Knots<'a, &'a mut [usize]>;
+pub type KnotsMut<'a, T> = Knots<'a, T, &'a mut [T]>;
 
-impl<'a, T> Knots<'a, T> {
+impl<'a, T, S> Knots<'a, T, S> {
     /// Create a new knot vector of degree.
-    pub fn new(degree: usize, knot_vec: T) -> Self {
+    pub fn new(degree: usize, knot_vec: S) -> Self {
         Self {
             degree,
             knot_vec,
@@ -25,13 +26,35 @@ impl<'a, T> Knots<'a, T> {
     }
 }
 
-impl Knots<'_> {
-    pub(crate) fn generate(degree: usize, num_points: usize) -> Vec<usize> {
-        Vec::from_iter(0..degree + num_points + 1)
+impl<T: Scalar> Knots<'_, T> {
+    pub(crate) fn generate(degree: usize, num_points: usize) -> Vec<T> {
+        Vec::from_iter((0..degree + num_points + 1).map(T::cast_from))
+    }
+
+    /// Knots of the reversed spline, mirrored within the same range.
+    pub(crate) fn reversed(&self) -> Vec<T> {
+        let (start, end) = self.range().into_inner();
+        self.knot_vec
+            .iter()
+            .rev()
+            .map(|&k| start + end - k)
+            .collect()
+    }
+
+    /// Knots mapped affinely so that the range becomes `range`, which must not be empty.
+    pub(crate) fn reparametrized(&self, range: RangeInclusive<T>) -> Vec<T> {
+        let (start, end) = self.range().into_inner();
+        let (new_start, new_end) = range.into_inner();
+        assert!(new_start < new_end, "range must start before it ends");
+        let scale = (new_end - new_start) / (end - start);
+        self.knot_vec
+            .iter()
+            .map(|&k| new_start + (k - start) * scale)
+            .collect()
     }
 }
 
-impl KnotsMut<'_> {
+impl<T: Scalar> KnotsMut<'_, T> {
     /// Clamp the ends of the knot vector so that the curve touches both the start and end control points.
     pub fn clamp_ends(&mut self) {
         let range = self.range();
@@ -43,7 +66,8 @@ impl KnotsMut<'_> {
         }
     }
 
-    /// Pinch a section of the knot vector.
+    /// Pinch a section of the knot vector, repeating the knot at `index` over the next
+    /// `length` knots and moving every knot after them down by `length`.
     /// ```
     /// use splinosaurus::knots::KnotsMut;
     /// let mut vec: Vec<usize> = vec![0,0,0,1,2,3,3,3];
@@ -58,12 +82,12 @@ impl KnotsMut<'_> {
             self[index + i] = self[index]
         }
         for i in index + length + 1..self.knot_vec.len() {
-            self[i] = self[i] - length;
+            self[i] -= T::cast_from(length);
         }
     }
 }
 
-impl<'a> IndexMut<usize> for KnotsMut<'a> {
+impl<'a, T: Scalar> IndexMut<usize> for KnotsMut<'a, T> {
     fn index_mut(&mut self, index: usize) -> &mut Self::Output {
         &mut self.knot_vec[index]
     }
@@ -71,14 +95,14 @@ impl<'a> IndexMut<usize> for KnotsMut<'a> {
 
 macro_rules! impl_knots {
     ($t:ty) => {
-        impl<'a> Knots<'a, $t> {
+        impl<'a, T: Scalar> Knots<'a, T, $t> {
             /// The range of a knot vector
-            pub fn range(&self) -> RangeInclusive<usize> {
+            pub fn range(&self) -> RangeInclusive<T> {
                 self.knot_vec[self.degree]..=self.knot_vec[self.knot_vec.len() - self.degree - 1]
             }
 
             /// Find a span within the knot vector
-            pub fn find_span(&self, u: usize) -> usize {
+            pub fn find_span(&self, u: T) -> usize {
                 let range = self.range();
                 if u == *range.end() {
                     // if we have the maximum u value then handle that as a special case;
@@ -111,8 +135,8 @@ macro_rules! impl_knots {
             }
         }
 
-        impl<'a> Index<usize> for Knots<'a, $t> {
-            type Output = usize;
+        impl<'a, T: Scalar> Index<usize> for Knots<'a, T, $t> {
+            type Output = T;
 
             fn index(&self, index: usize) -> &Self::Output {
                 &self.knot_vec[index]
@@ -121,8 +145,8 @@ macro_rules! impl_knots {
     };
 }
 
-impl_knots!(&'a [usize]);
-impl_knots!(&'a mut [usize]);
+impl_knots!(&'a [T]);
+impl_knots!(&'a mut [T]);
 
 #[cfg(test)]
 mod tests {
@@ -21,10 +21,14 @@
 extern crate alloc;
 extern crate core;
 
+/// Bounding volume hierarchies for repeated queries against surfaces.
+pub mod bvh;
 /// Defining and manipulating control points.
 pub mod control_points;
 /// Exporting to other formats such as meshes.
 pub mod export;
+/// Intersections between splines, surfaces and geometric primitives.
+pub mod intersect;
 /// Defining and manipulating knots.
 pub mod knots;
 /// N dimensional splines.
@@ -33,6 +37,9 @@ pub mod splines;
 pub mod surfaces;
 
 mod algorithms;
+mod bezier;
 mod grid;
+mod rational;
 mod step_iter;
+mod transform;
 mod types;
@@ -0,0 +1,259 @@
+use crate::bezier::BezierPatch;
+use crate::export::BoundingBox;
+use crate::surfaces::{BSurface, NURBSurface, UV};
+use crate::types::Real;
+use alloc::vec::Vec;
+use core::ops::RangeInclusive;
+use nalgebra::{Const, Matrix2, Vector2, Vector3, Vector4};
+
+/// A 3D surface made of rational Bezier patches, either a 3D `BSurface` or a 4D `NURBSurface`.
+/// The trait is sealed, it is implemented for exactly those surfaces.
+pub trait RationalSurface<T: Real>: sealed::Sealed<T> {}
+
+mod sealed {
+    use super::*;
+
+    pub trait Sealed<T: Real> {
+        /// Bezier patches with control points in homogeneous coordinates.
+        fn rational_patches(&self) -> Vec<BezierPatch<T, Vector4<T>>>;
+        /// If the surface wraps in the u and v directions.
+        fn wrapping(&self) -> UV<bool>;
+    }
+}
+
+impl<T: Real> RationalSurface<T> for BSurface<Const<3>, T> {}
+
+impl<T: Real> RationalSurface<T> for NURBSurface<'_, Const<4>, T> {}
+
+impl<T: Real> sealed::Sealed<T> for BSurface<Const<3>, T> {
+    fn rational_patches(&self) -> Vec<BezierPatch<T, Vector4<T>>> {
+        self.bezier_patches(|p| p.push(T::cast_from(1)))
+    }
+
+    fn wrapping(&self) -> UV<bool> {
+        let grid = self.control_grid();
+        (grid.u_wrapping(), grid.v_wrapping())
+    }
+}
+
+impl<T: Real> sealed::Sealed<T> for NURBSurface<'_, Const<4>, T> {
+    fn rational_patches(&self) -> Vec<BezierPatch<T, Vector4<T>>> {
+        self.spline().bezier_patches(|p| (p.xyz() * p.w).push(p.w))
+    }
+
+    fn wrapping(&self) -> UV<bool> {
+        let grid = self.spline().control_grid();
+        (grid.u_wrapping(), grid.v_wrapping())
+    }
+}
+
+/// Rational patches of a surface used to evaluate points and derivatives.
+#[derive(Debug, Clone)]
+pub struct Patches<T: Real> {
+    patches: Vec<BezierPatch<T, Vector4<T>>>,
+    u_breaks: Vec<T>,
+    v_breaks: Vec<T>,
+    wrapping: UV<bool>,
+}
+
+impl<T: Real> Patches<T> {
+    pub fn new(surface: &(impl RationalSurface<T> + ?Sized)) -> Self {
+        let patches = surface.rational_patches();
+
+        let mut u_breaks: Vec<_> = patches.iter().flat_map(|p| [p.u.0, p.u.1]).collect();
+        let mut v_breaks: Vec<_> = patches.iter().flat_map(|p| [p.v.0, p.v.1]).collect();
+        for breaks in [&mut u_breaks, &mut v_breaks] {
+            breaks.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            breaks.dedup();
+        }
+
+        Self {
+            patches,
+            u_breaks,
+            v_breaks,
+            wrapping: surface.wrapping(),
+        }
+    }
+
+    /// All the patches ordered by u then v.
+    pub fn patches(&self) -> &[BezierPatch<T, Vector4<T>>] {
+        &self.patches
+    }
+
+    /// If the surface wraps in the u and v directions.
+    pub fn wrapping(&self) -> UV<bool> {
+        self.wrapping
+    }
+
+    /// Usable range of u values.
+    pub fn u_range(&self) -> RangeInclusive<T> {
+        self.u_breaks[0]..=self.u_breaks[self.u_breaks.len() - 1]
+    }
+
+    /// Usable range of v values.
+    pub fn v_range(&self) -> RangeInclusive<T> {
+        self.v_breaks[0]..=self.v_breaks[self.v_breaks.len() - 1]
+    }
+
+    /// Move `uv` into range, going around the surface along the directions which wrap.
+    pub fn clamp(&self, (u, v): UV<T>) -> UV<T> {
+        let clamp = |x: T, wrapping: bool, range: RangeInclusive<T>| {
+            let (start, end) = range.into_inner();
+            if wrapping && (x < start || x > end) {
+                let period = end - start;
+                x - ((x - start) / period).floor() * period
+            } else {
+                x.clamp(start, end)
+            }
+        };
+        (
+            clamp(u, self.wrapping.0, self.u_range()),
+            clamp(v, self.wrapping.1, self.v_range()),
+        )
+    }
+
+    /// The patch containing `uv`.
+    pub fn patch(&self, (u, v): UV<T>) -> &BezierPatch<T, Vector4<T>> {
+        let span = |breaks: &[T], x: T| {
+            breaks[1..breaks.len() - 1]
+                .partition_point(|&b| b <= x)
+                .min(breaks.len() - 2)
+        };
+        let v_spans = self.v_breaks.len() - 1;
+        &self.patches[span(&self.u_breaks, u) * v_spans + span(&self.v_breaks, v)]
+    }
+
+    /// Point at `uv`.
+    pub fn at(&self, uv: UV<T>) -> Vector3<T> {
+        self.derivatives(uv).0
+    }
+
+    /// Point and its partial derivatives in u and v at `uv`.
+    pub fn derivatives(&self, uv: UV<T>) -> (Vector3<T>, Vector3<T>, Vector3<T>) {
+        let patch = self.patch(uv);
+        project(patch.derivatives(patch.local(uv)))
+    }
+
+    /// Unnormalized surface normal at `uv`.
+    pub fn normal(&self, uv: UV<T>) -> Vector3<T> {
+        let (_, du, dv) = self.derivatives(uv);
+        du.cross(&dv)
+    }
+
+    /// Coordinate of the point within `patch` closest to `point`.
+    pub fn closest(&self, patch: &BezierPatch<T, Vector4<T>>, point: Vector3<T>) -> UV<T> {
+        let distance = |uv: UV<T>| (self.at(uv) - point).norm_squared();
+        let clamp = |(u, v): UV<T>| (u.clamp(patch.u.0, patch.u.1), v.clamp(patch.v.0, patch.v.1));
+
+        // start from the nearest of a few samples to avoid local minima
+        let samples = 4;
+        let mut best = patch.parameter((T::zero(), T::zero()));
+        for j in 0..=samples {
+            for i in 0..=samples {
+                let local = (
+                    T::cast_from(i) / T::cast_from(samples),
+                    T::cast_from(j) / T::cast_from(samples),
+                );
+                let uv = patch.parameter(local);
+                if distance(uv) < distance(best) {
+                    best = uv;
+                }
+            }
+        }
+
+        // gauss newton steps halved until they get closer
+        for _ in 0..32 {
+            let (p, du, dv) = self.derivatives(best);
+            let r = p - point;
+            let normal = Matrix2::new(du.dot(&du), du.dot(&dv), du.dot(&dv), dv.dot(&dv));
+            let Some(inverse) = normal.try_inverse() else {
+                break;
+            };
+            let mut delta = inverse * Vector2::new(du.dot(&r), dv.dot(&r));
+
+            let current = distance(best);
+            let mut improved = None;
+            for _ in 0..8 {
+                let uv = clamp((best.0 - delta.x, best.1 - delta.y));
+                if distance(uv) < current {
+                    improved = Some(uv);
+                    break;
+                }
+                delta /= T::cast_from(2);
+            }
+            match improved {
+                Some(uv) => best = uv,
+                None => break,
+            }
+        }
+        best
+    }
+}
+
+/// Project a homogeneous point and its partial derivatives into 3D.
+pub fn project<T: Real>(
+    (point, du, dv): (Vector4<T>, Vector4<T>, Vector4<T>),
+) -> (Vector3<T>, Vector3<T>, Vector3<T>) {
+    let p = point.xyz() / point.w;
+    (
+        p,
+        (du.xyz() - p * du.w) / point.w,
+        (dv.xyz() - p * dv.w) / point.w,
+    )
+}
+
+/// Bounding box of the control points of a patch which contains the whole patch.
+pub fn bounding_box<T: Real>(patch: &BezierPatch<T, Vector4<T>>) -> BoundingBox<Const<3>, T> {
+    let points: Vec<_> = patch
+        .points
+        .as_ref()
+        .iter()
+        .map(|p| p.xyz() / p.w)
+        .collect();
+    BoundingBox::new(&points)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::control_points::ControlGrid;
+    use crate::surfaces::Surface;
+    use alloc::vec;
+
+    #[test]
+    fn it_matches_surfaces() {
+        let mut grid = ControlGrid::new(
+            2,
+            3,
+            vec![
+                Vector4::new(0f64, 0., 0., 1.),
+                Vector4::new(1., 0., 1., 2.),
+                Vector4::new(2., 0., 0., 1.),
+                Vector4::new(0., 1., 1., 1.),
+                Vector4::new(1., 1., 2., 0.5),
+                Vector4::new(2., 1., 1., 1.),
+                Vector4::new(0., 2., 0., 1.),
+                Vector4::new(1., 2., 1., 1.),
+                Vector4::new(2., 2., 0., 3.),
+            ],
+        );
+        grid.set_u_wrapping(true);
+        let surface = BSurface::new(grid);
+        let nurbs = surface.nurbs();
+        let patches = Patches::new(&nurbs);
+
+        let h = 1e-6f64;
+        for (u, v) in nurbs.quantize_range(0.25) {
+            let (point, du, dv) = patches.derivatives((u, v));
+            assert!((nurbs.at((u, v)) - point).norm() < 1e-9);
+
+            let (u, v) = (u.min(5. - h), v.min(3. - h));
+            let fd_u = (nurbs.at((u + h, v)) - nurbs.at((u, v))) / h;
+            let fd_v = (nurbs.at((u, v + h)) - nurbs.at((u, v))) / h;
+            let (_, du_at, dv_at) = patches.derivatives((u, v));
+            assert!((fd_u - du_at).norm() < 1e-4);
+            assert!((fd_v - dv_at).norm() < 1e-4);
+            assert!((du - du_at).norm() < 1e-4 && (dv - dv_at).norm() < 1e-4);
+        }
+    }
+}
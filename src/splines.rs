@@ -1,13 +1,23 @@
+pub(crate) mod approximate;
 mod b_spline;
+mod conics;
+mod curve_on_surface;
+mod interpolate;
 mod nurbs;
+mod offset;
+mod smoothing;
 
 use crate::types::{Scalar, Vector};
 use core::ops::RangeInclusive;
 use nalgebra::{DefaultAllocator, Dim};
 
 use crate::step_iter::StepIter;
-pub use b_spline::BSpline;
+pub use approximate::Approximation;
+pub use b_spline::{BSpline, Continuity};
+pub use curve_on_surface::CurveOnSurface;
+pub use interpolate::{Interpolation, Parametrization};
 pub use nurbs::NURBSpline;
+pub use smoothing::{Penalty, Smoothing};
 
 /// A single dimensional spline.
 pub trait Spline<D: Dim, T: Scalar + 'static>: Sized
@@ -29,3 +39,12 @@ where
         self.quantize_range(step).map(|i| self.at(i))
     }
 }
+
+/// A single dimensional spline which can be differentiated.
+pub trait SplineDerivative<D: Dim, T: Scalar + 'static>: Spline<D, T>
+where
+    DefaultAllocator: nalgebra::allocator::Allocator<T, D>,
+{
+    /// First derivative at position `u`.
+    fn derivative(&self, u: T) -> Vector<D, T>;
+}
@@ -0,0 +1,371 @@
+use crate::algorithms::{averaged_knots, basis_functions, solve};
+use crate::control_points::ControlVec;
+use crate::knots::Knots;
+use crate::splines::{BSpline, Parametrization, Spline};
+use crate::types::{Real, Vector};
+use alloc::vec::Vec;
+use nalgebra::allocator::Allocator;
+use nalgebra::{DMatrix, DefaultAllocator, Dim};
+
+impl<D: Dim, T: Real> BSpline<D, T>
+where
+    DefaultAllocator: Allocator<T, D>,
+{
+    /// Spline of `degree` with `count` control points over the range zero to one which
+    /// passes as closely as possible to the points in the least squares sense.
+    /// Returned with the largest distance from a point to the spline at its parameter,
+    /// or `None` if the fit cannot be solved.
+    pub fn approximate(points: &[Vector<D, T>], degree: usize, count: usize) -> Option<(Self, T)> {
+        Approximation::new(points, count).degree(degree).build()
+    }
+
+    /// Spline of `degree` over the range zero to one from its first to its last point,
+    /// passing within `tolerance` of every point at its parameter. Starting from a single
+    /// span, a knot is added among the points inside the span furthest from its points
+    /// until they are all close enough, so that the spline has few control points.
+    /// Returned with the largest distance from a point to the spline at its parameter,
+    /// or `None` if the fit cannot be solved.
+    pub fn fit(points: &[Vector<D, T>], degree: usize, tolerance: T) -> Option<(Self, T)> {
+        assert!(degree > 0, "cannot fit with degree 0");
+        assert!(points.len() > degree, "not enough points for the degree");
+        let parameters = Parametrization::ChordLength.parameters(points, false);
+        let (start, end) = (parameters[0], parameters[parameters.len() - 1]);
+        let mut knots = alloc::vec![start; degree + 1];
+        knots.extend(core::iter::repeat_n(end, degree + 1));
+
+        // once there is a control point for every point the spline passes through them
+        let exact = || {
+            Approximation::new(points, points.len())
+                .degree(degree)
+                .fixed_ends(true)
+                .build()
+        };
+        while knots.len() - degree - 1 < points.len() {
+            let Some((spline, error)) =
+                least_squares(points, &parameters, None, None, true, degree, knots.clone())
+            else {
+                return exact();
+            };
+            if error <= tolerance {
+                return Some((spline, error));
+            }
+
+            // split the span furthest from its points at the middle of the points inside it,
+            // leaving out spans without points inside, which cannot be split into spans
+            // that both hold points
+            let k = spline.knots();
+            let inside = |u: T, span: usize| u > knots[span] && u < knots[span + 1];
+            let mut errors = alloc::vec![(T::zero(), false); knots.len()];
+            for (point, &u) in points.iter().zip(&parameters) {
+                let span = k.find_span(u);
+                let distance = (spline.at(u) - point).norm();
+                errors[span] = (
+                    errors[span].0.max(distance),
+                    errors[span].1 || inside(u, span),
+                );
+            }
+            let Some(span) = (0..errors.len())
+                .filter(|&span| errors[span].1)
+                .reduce(|a, b| if errors[b].0 > errors[a].0 { b } else { a })
+            else {
+                return exact();
+            };
+            let inside: Vec<_> = parameters.iter().filter(|&&u| inside(u, span)).collect();
+            let knot = *inside[inside.len() / 2];
+            knots.insert(span + 1, knot);
+        }
+        exact()
+    }
+}
+
+/// Builder of a least squares fit of a spline with a fixed number of control points to
+/// points, which may be weighted and may have the ends of the spline fixed to the first
+/// and last points.
+#[derive(Debug, Clone)]
+pub struct Approximation<'a, D: Dim, T: Real>
+where
+    DefaultAllocator: Allocator<T, D>,
+{
+    points: &'a [Vector<D, T>],
+    count: usize,
+    degree: usize,
+    parametrization: Parametrization,
+    weights: Option<&'a [T]>,
+    fixed_ends: bool,
+}
+
+impl<'a, D: Dim, T: Real> Approximation<'a, D, T>
+where
+    DefaultAllocator: Allocator<T, D>,
+{
+    /// Approximate `points` by a cubic spline with `count` control points and chord
+    /// length parametrization.
+    pub fn new(points: &'a [Vector<D, T>], count: usize) -> Self {
+        Self {
+            points,
+            count,
+            degree: 3,
+            parametrization: Parametrization::ChordLength,
+            weights: None,
+            fixed_ends: false,
+        }
+    }
+
+    /// Set the degree of the spline.
+    pub fn degree(mut self, degree: usize) -> Self {
+        self.degree = degree;
+        self
+    }
+
+    /// Set how the points are spread over the range of the spline.
+    pub fn parametrization(mut self, parametrization: Parametrization) -> Self {
+        self.parametrization = parametrization;
+        self
+    }
+
+    /// Weigh how much each point counts towards the fit, one weight per point.
+    pub fn weights(mut self, weights: &'a [T]) -> Self {
+        assert_eq!(self.points.len(), weights.len(), "one weight per point");
+        self.weights = Some(weights);
+        self
+    }
+
+    /// Set if the spline starts exactly at the first point and ends at the last.
+    pub fn fixed_ends(mut self, fixed_ends: bool) -> Self {
+        self.fixed_ends = fixed_ends;
+        self
+    }
+
+    /// Least squares fit of the spline, returned with the largest distance from a point
+    /// to the spline at its parameter, or `None` if the fit cannot be solved.
+    pub fn build(self) -> Option<(BSpline<D, T>, T)> {
+        let (points, count, degree) = (self.points, self.count, self.degree);
+        assert!(degree > 0, "cannot approximate with degree 0");
+        assert!(count > degree, "not enough control points for the degree");
+        assert!(count <= points.len(), "more control points than points");
+        let parameters = self.parametrization.parameters(points, false);
+        let knots = approximation_knots(&parameters, degree, count);
+        least_squares(
+            points,
+            &parameters,
+            self.weights,
+            None,
+            self.fixed_ends,
+            degree,
+            knots,
+        )
+    }
+}
+
+/// Least squares fit of a clamped spline with `knots` to `points` at `parameters`,
+/// returned with the largest distance from a point to the spline at its parameter.
+/// A `penalty` on the control points, a quadratic form over all of them, is added to
+/// the squared distances.
+pub(crate) fn least_squares<D: Dim, T: Real>(
+    points: &[Vector<D, T>],
+    parameters: &[T],
+    weights: Option<&[T]>,
+    penalty: Option<&DMatrix<T>>,
+    fixed_ends: bool,
+    degree: usize,
+    knots: Vec<T>,
+) -> Option<(BSpline<D, T>, T)>
+where
+    DefaultAllocator: Allocator<T, D>,
+{
+    let count = knots.len() - degree - 1;
+    let k = Knots::new(degree, knots.as_slice());
+
+    // with fixed ends only the control points between them are solved for
+    let fixed = usize::from(fixed_ends);
+    let unknowns = count - 2 * fixed;
+    let first = points[0].clone();
+    let last = points[points.len() - 1].clone();
+    let mut normal = DMatrix::zeros(unknowns, unknowns);
+    let mut values = alloc::vec![&first * T::zero(); unknowns];
+    for (index, &u) in parameters.iter().enumerate() {
+        let weight = weights.map_or(T::cast_from(1), |w| w[index]);
+        let span = k.find_span(u);
+        let basis = basis_functions(u, span, degree, &k);
+
+        let mut residual = points[index].clone();
+        let mut columns = Vec::with_capacity(basis.len());
+        for (i, &value) in basis.iter().enumerate() {
+            let column = span - degree + i;
+            if fixed_ends && column == 0 {
+                residual -= &first * value;
+            } else if fixed_ends && column == count - 1 {
+                residual -= &last * value;
+            } else {
+                columns.push((column - fixed, value));
+            }
+        }
+
+        for &(row, a) in &columns {
+            values[row] += &residual * (a * weight);
+            for &(column, b) in &columns {
+                normal[(row, column)] += a * b * weight;
+            }
+        }
+    }
+    if let Some(penalty) = penalty {
+        for row in 0..unknowns {
+            if fixed_ends {
+                values[row] -= &first * penalty[(row + 1, 0)];
+                values[row] -= &last * penalty[(row + 1, count - 1)];
+            }
+            for column in 0..unknowns {
+                normal[(row, column)] += penalty[(row + fixed, column + fixed)];
+            }
+        }
+    }
+
+    let mut control = if unknowns > 0 {
+        solve(normal, &values)?
+    } else {
+        Vec::new()
+    };
+    if fixed_ends {
+        control.insert(0, first);
+        control.push(last);
+    }
+
+    let spline = BSpline::with_knots(ControlVec::new(degree, control), knots);
+    let error = points
+        .iter()
+        .zip(parameters)
+        .map(|(point, &u)| (spline.at(u) - point).norm())
+        .fold(T::zero(), |a, b| a.max(b));
+    Some((spline, error))
+}
+
+/// Clamped knots for `count` control points, spread so that every span holds some
+/// of the parameters. With a control point for every parameter these are the knots
+/// which interpolate.
+pub(crate) fn approximation_knots<T: Real>(
+    parameters: &[T],
+    degree: usize,
+    count: usize,
+) -> Vec<T> {
+    if count == parameters.len() {
+        return averaged_knots(parameters, degree);
+    }
+    let spans = count - degree;
+    let ratio = T::cast_from(parameters.len()) / T::cast_from(spans);
+    let mut knots = alloc::vec![parameters[0]; degree + 1];
+    for j in 1..spans {
+        let position = T::cast_from(j) * ratio;
+        let i: usize = position.floor().cast();
+        let alpha = position - T::cast_from(i);
+        knots.push(parameters[i - 1] * (T::cast_from(1) - alpha) + parameters[i] * alpha);
+    }
+    knots.extend(core::iter::repeat_n(
+        parameters[parameters.len() - 1],
+        degree + 1,
+    ));
+    knots
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::Vector2;
+
+    fn samples() -> Vec<Vector2<f64>> {
+        (0..200)
+            .map(|i| {
+                let x = i as f64 / 20.;
+                let noise = ((i * 37 % 11) as f64 - 5.) / 500.;
+                Vector2::new(x, x.sin() + noise)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn it_approximates_noisy_points() {
+        let points = samples();
+        let (spline, error) = BSpline::approximate(&points, 3, 20).unwrap();
+
+        assert_eq!(20, spline.control_points().len());
+        assert_eq!(0.0..=1.0, spline.range());
+        // the noise is smoothed out, following the underlying curve
+        assert!(error < 0.02);
+        for i in 0..=100 {
+            let p = spline.at(i as f64 / 100.);
+            assert!((p.y - p.x.sin()).abs() < 0.02);
+        }
+        let parameters = Parametrization::ChordLength.parameters(&points, false);
+        let largest = points
+            .iter()
+            .zip(&parameters)
+            .map(|(p, &u)| (spline.at(u) - p).norm())
+            .fold(0., f64::max);
+        assert_eq!(largest, error);
+
+        // with as many control points as points the fit passes through them
+        let few = &points[..30];
+        let (exact, error) = BSpline::approximate(few, 3, few.len()).unwrap();
+        assert!(error < 1e-9);
+        assert_eq!(few.len(), exact.control_points().len());
+    }
+
+    #[test]
+    fn it_fixes_ends_and_weighs_points() {
+        let points = samples();
+        let (spline, _) = Approximation::new(&points, 8)
+            .fixed_ends(true)
+            .build()
+            .unwrap();
+        assert_eq!(points[0], spline.at(0.));
+        assert_eq!(points[points.len() - 1], spline.at(1.));
+
+        let parameters = Parametrization::ChordLength.parameters(&points, false);
+        let mut weights = alloc::vec![1.; points.len()];
+        weights[100] = 1000.;
+        let (plain, _) = Approximation::new(&points, 6).build().unwrap();
+        let (weighted, _) = Approximation::new(&points, 6)
+            .weights(&weights)
+            .build()
+            .unwrap();
+        let distance =
+            |s: &BSpline<nalgebra::Const<2>, f64>| (s.at(parameters[100]) - points[100]).norm();
+        assert!(distance(&weighted) < distance(&plain) / 10.);
+    }
+
+    #[test]
+    fn it_fits_within_tolerance() {
+        let points = samples();
+        for tolerance in [0.1, 0.02] {
+            let (spline, error) = BSpline::fit(&points, 3, tolerance).unwrap();
+            assert!(error <= tolerance);
+            assert_eq!(points[0], spline.at(0.));
+            assert_eq!(points[points.len() - 1], spline.at(1.));
+            assert!(spline.control_points().len() < 40);
+        }
+
+        // a straight line needs a single span
+        let line: Vec<_> = (0..50)
+            .map(|i| Vector2::new(i as f64, 2. * i as f64))
+            .collect();
+        let (spline, error) = BSpline::fit(&line, 2, 1e-9).unwrap();
+        assert!(error <= 1e-9);
+        assert_eq!(3, spline.control_points().len());
+
+        // the noise cannot be followed more closely than it is sampled
+        let (spline, error) = BSpline::fit(&points[..30], 3, 0.).unwrap();
+        assert!(error < 1e-9);
+        assert_eq!(30, spline.control_points().len());
+    }
+
+    #[test]
+    fn it_splits_spans_holding_points() {
+        // splitting the worst span here would leave a span without points, which cannot be
+        // fitted, so a span with points inside it is split instead
+        let points: Vec<_> = (0..9)
+            .map(|i| Vector2::new(i as f64, (i * 4 % 5) as f64))
+            .collect();
+        let (spline, error) = BSpline::fit(&points, 3, 0.05).unwrap();
+        assert!(error <= 0.05);
+        assert!(spline.control_points().len() < points.len());
+    }
+}
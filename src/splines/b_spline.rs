@@ -1,14 +1,26 @@
-use crate::algorithms::cox_de_boor_u;
+use crate::algorithms::{cox_de_boor_u, elevate, insert_knot, remove_knot, trim};
+use crate::bezier::{decompose, Bezier, Linear};
 use crate::control_points::ControlVec;
 use crate::knots::{Knots, KnotsMut};
-use crate::splines::{NURBSpline, Spline};
-use crate::types::{Scalar, Vector};
+use crate::splines::{NURBSpline, Spline, SplineDerivative};
+use crate::types::{Real, Scalar, Vector};
 use alloc::vec;
 use alloc::vec::Vec;
 use core::ops::RangeInclusive;
 use nalgebra::allocator::Allocator;
 use nalgebra::{Const, DefaultAllocator, Dim, Vector2, Vector3};
 
+/// How smoothly two joined curves meet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Continuity {
+    /// The curves share the point where they meet.
+    C0,
+    /// The curves also share the direction of their tangent.
+    G1,
+    /// The curves also share their first derivative.
+    C1,
+}
+
 /// Basis spline of a single degree.
 /// https://en.wikipedia.org/wiki/B-spline
 #[derive(Debug, Clone)]
@@ -17,7 +29,7 @@ where
     DefaultAllocator: Allocator<T, D>,
 {
     control_points: ControlVec<Vector<D, T>>,
-    knots: Vec<usize>,
+    knots: Vec<T>,
 }
 
 impl BSpline<Const<2>, f64> {
@@ -77,13 +89,27 @@ where
         }
     }
 
+    /// Create a new basis spline with explicit knots, which must number the control points
+    /// including wrapping plus the degree plus one.
+    pub fn with_knots(control_points: ControlVec<Vector<D, T>>, knots: Vec<T>) -> Self {
+        assert_eq!(
+            control_points.len() + control_points.degree() + 1,
+            knots.len(),
+            "wrong number of knots"
+        );
+        Self {
+            control_points,
+            knots,
+        }
+    }
+
     /// Access the knots of the spline.
-    pub fn knots(&self) -> Knots {
+    pub fn knots(&self) -> Knots<'_, T> {
         Knots::new(self.control_points.degree(), &self.knots)
     }
 
     /// Mutable access to the knots of the spline.
-    pub fn knots_mut(&mut self) -> KnotsMut {
+    pub fn knots_mut(&mut self) -> KnotsMut<'_, T> {
         Knots::new(self.control_points.degree(), &mut self.knots)
     }
 
@@ -109,9 +135,291 @@ where
 
     /// Convert an N degree BSpline into a N-1 degree NURBS.
     /// The final degree becomes the weight value.
-    pub fn nurbs(&self) -> NURBSpline<D, T> {
+    pub fn nurbs(&self) -> NURBSpline<'_, D, T> {
         NURBSpline::new(self)
     }
+
+    /// The same curve traversed in the opposite direction over the same range.
+    pub fn reversed(&self) -> Self {
+        Self {
+            control_points: self.control_points.reversed(),
+            knots: self.knots().reversed(),
+        }
+    }
+
+    /// The same curve with the knots mapped so that the range becomes `range`.
+    pub fn reparametrized(&self, range: RangeInclusive<T>) -> Self {
+        Self {
+            control_points: self.control_points.clone(),
+            knots: self.knots().reparametrized(range),
+        }
+    }
+
+    /// The same curve with the knots mapped so that the range becomes zero to one.
+    pub fn normalized(&self) -> Self {
+        self.reparametrized(T::cast_from(0)..=T::cast_from(1))
+    }
+
+    /// Split the spline at `u` into clamped splines covering the range before and after it.
+    /// There is nothing after `u` to split off when it is at either end of the range, so the
+    /// whole spline comes back clamped with no second part. A wrapping spline is cut open at
+    /// `u` instead, into a single clamped spline going once around from `u`.
+    pub fn split_at(&self, u: T) -> (Self, Option<Self>) {
+        self.split_mapped(u, Clone::clone, Clone::clone)
+    }
+
+    /// Standalone clamped spline covering `range`, which lies within the range of the spline.
+    pub fn subcurve(&self, range: RangeInclusive<T>) -> Self {
+        self.subcurve_mapped(range, Clone::clone, Clone::clone)
+    }
+
+    /// The same curve as a clamped spline of one degree higher.
+    pub fn elevate_degree(&self) -> Self {
+        self.elevate_mapped(Clone::clone, Clone::clone)
+    }
+
+    /// Bezier segments covering the range of the spline with each control point mapped by `f`.
+    pub(crate) fn bezier_segments<P: Linear<T>>(
+        &self,
+        f: impl Fn(&Vector<D, T>) -> P,
+    ) -> Vec<Bezier<T, P>> {
+        let (knots, points) = self.expanded(f);
+        decompose(self.degree(), knots, points)
+    }
+
+    /// Split with the control points mapped by `f` while inserting knots and by `g` after.
+    pub(crate) fn split_mapped(
+        &self,
+        u: T,
+        f: impl Fn(&Vector<D, T>) -> Vector<D, T>,
+        g: impl Fn(&Vector<D, T>) -> Vector<D, T>,
+    ) -> (Self, Option<Self>) {
+        let (start, end) = self.range().into_inner();
+        assert!(start <= u && u <= end, "u out of range");
+        if self.control_points.wrapping() {
+            (self.open_mapped(u, f, g), None)
+        } else if u == start || u == end {
+            (self.subcurve_mapped(start..=end, f, g), None)
+        } else {
+            (
+                self.subcurve_mapped(start..=u, &f, &g),
+                Some(self.subcurve_mapped(u..=end, &f, &g)),
+            )
+        }
+    }
+
+    /// Subcurve with the control points mapped by `f` while inserting knots and by `g` after.
+    pub(crate) fn subcurve_mapped(
+        &self,
+        range: RangeInclusive<T>,
+        f: impl Fn(&Vector<D, T>) -> Vector<D, T>,
+        g: impl Fn(&Vector<D, T>) -> Vector<D, T>,
+    ) -> Self {
+        let (start, end) = range.into_inner();
+        let (first, last) = self.range().into_inner();
+        assert!(
+            first <= start && start < end && end <= last,
+            "range out of range"
+        );
+
+        let (knots, points) = self.expanded(f);
+        self.rebuild(trim(self.degree(), knots, points, (start, end)), g)
+    }
+
+    /// Elevate with the control points mapped by `f` while elevating and by `g` after.
+    pub(crate) fn elevate_mapped(
+        &self,
+        f: impl Fn(&Vector<D, T>) -> Vector<D, T>,
+        g: impl Fn(&Vector<D, T>) -> Vector<D, T>,
+    ) -> Self {
+        let (knots, points) = self.expanded(f);
+        let (knots, points) = elevate(self.degree(), knots, points);
+        let points = points.iter().map(g).collect();
+        Self::with_knots(ControlVec::new(self.degree() + 1, points), knots)
+    }
+
+    /// Open with the control points mapped by `f` while inserting knots and by `g` after.
+    pub(crate) fn open_mapped(
+        &self,
+        u: T,
+        f: impl Fn(&Vector<D, T>) -> Vector<D, T>,
+        g: impl Fn(&Vector<D, T>) -> Vector<D, T>,
+    ) -> Self {
+        assert!(self.control_points.wrapping(), "spline does not wrap");
+        let range = self.range();
+        assert!(range.contains(&u), "u out of range");
+
+        // continue the knots and points for another period so that it covers `u` onwards,
+        // knots past the end of the range are repeated from the start
+        let (mut knots, mut points) = self.expanded(&f);
+        let n = self.control_points.points().len();
+        let period = *range.end() - *range.start();
+        knots.truncate(points.len() + 1);
+        while knots.len() < points.len() + n + self.degree() + 1 {
+            knots.push(knots[knots.len() - n] + period);
+        }
+        for _ in 0..n {
+            points.push(f(&self.control_points[points.len()]));
+        }
+        self.rebuild(trim(self.degree(), knots, points, (u, u + period)), &g)
+    }
+
+    /// Derivative at `u` with the control points mapped by `f`.
+    pub(crate) fn derivative_mapped(
+        &self,
+        u: T,
+        f: impl Fn(&Vector<D, T>) -> Vector<D, T>,
+    ) -> Vector<D, T> {
+        let degree = self.degree();
+        let point = |i: usize| f(&self.control_points[i]);
+        if degree == 0 {
+            return point(0) * T::default();
+        }
+
+        // the derivative is a spline of one degree lower over the inner knots
+        let knots = Knots::new(degree - 1, &self.knots[1..self.knots.len() - 1]);
+        cox_de_boor_u(u, degree - 1, &knots, |i| {
+            let span = self.knots[i + degree + 1] - self.knots[i + 1];
+            if span == T::default() {
+                point(i) * T::default()
+            } else {
+                (point(i + 1) - point(i)) * (T::cast_from(degree) / span)
+            }
+        })
+    }
+
+    /// Knots and control points with wrapping expanded and each point mapped by `f`.
+    fn expanded<P>(&self, f: impl Fn(&Vector<D, T>) -> P) -> (Vec<T>, Vec<P>) {
+        let points = (0..self.control_points.len())
+            .map(|i| f(&self.control_points[i]))
+            .collect();
+        (self.knots.clone(), points)
+    }
+
+    fn rebuild(
+        &self,
+        (knots, points): (Vec<T>, Vec<Vector<D, T>>),
+        g: impl Fn(&Vector<D, T>) -> Vector<D, T>,
+    ) -> Self {
+        let points = points.iter().map(g).collect();
+        Self::with_knots(ControlVec::new(self.degree(), points), knots)
+    }
+}
+
+impl<D: Dim, T: Real> BSpline<D, T>
+where
+    DefaultAllocator: Allocator<T, D>,
+{
+    /// Append `other` after the end of this spline, provided it starts within `tolerance`
+    /// of where this one ends. The lower degree spline is elevated to match and `other` is
+    /// moved to continue the range, with its start moved onto the end of this spline. The
+    /// splines meet with C1 if the knot where they meet can be removed without moving the
+    /// curve by more than `tolerance`, and with G1 if the control points next to where they
+    /// meet are within `tolerance` of sharing a direction from it.
+    pub fn join(&self, other: &Self, tolerance: T) -> Option<(Self, Continuity)> {
+        let (end, start) = (*self.range().end(), *other.range().start());
+        if (self.at(end) - other.at(start)).norm() > tolerance {
+            return None;
+        }
+        let (first, second) = self.matched(other, Clone::clone);
+        Some(Self::joined(first, second, tolerance, Clone::clone, |p| {
+            (p.clone(), T::cast_from(1))
+        }))
+    }
+
+    /// Clamped splines of this spline and `other`, with the control points mapped by `f`,
+    /// elevated to the same degree.
+    pub(crate) fn matched(
+        &self,
+        other: &Self,
+        f: impl Fn(&Vector<D, T>) -> Vector<D, T>,
+    ) -> (Self, Self) {
+        let mut first = self.subcurve_mapped(self.range(), &f, Clone::clone);
+        let mut second = other.subcurve_mapped(other.range(), &f, Clone::clone);
+        while first.degree() < second.degree() {
+            first = first.elevate_degree();
+        }
+        while second.degree() < first.degree() {
+            second = second.elevate_degree();
+        }
+        (first, second)
+    }
+
+    /// Join of the clamped splines `first` and `second` of the same degree, with the start
+    /// of `second` moved onto the end of `first` and the control points mapped by `g` after.
+    /// The continuity is found from the mapped points, split by `position` into the point
+    /// and its weight.
+    pub(crate) fn joined(
+        first: Self,
+        mut second: Self,
+        tolerance: T,
+        g: impl Fn(&Vector<D, T>) -> Vector<D, T>,
+        position: impl Fn(&Vector<D, T>) -> (Vector<D, T>, T),
+    ) -> (Self, Continuity) {
+        let degree = first.degree();
+        assert!(degree > 0, "cannot join splines of degree 0");
+        let (end, start) = (*first.range().end(), *second.range().start());
+
+        let n = first.knots.len() - degree - 1;
+        second.control_points_mut()[0] = first.control_points()[n - 1].clone();
+        let mut knots = first.knots;
+        knots.pop();
+        knots.extend(second.knots[degree + 1..].iter().map(|&k| k - start + end));
+        let mut points = Vec::from(first.control_points.points());
+        points.extend_from_slice(&second.control_points.points()[1..]);
+
+        // remove the knot where they meet if inserting it again gives back the same curve
+        let mut removed = (knots.clone(), points.clone());
+        let r = knots.iter().rposition(|&k| k == end).unwrap();
+        remove_knot(degree, &mut removed.0, &mut removed.1, r);
+        let mut inserted = removed.clone();
+        let span = inserted.0.iter().rposition(|&k| k <= end).unwrap();
+        insert_knot(degree, &mut inserted.0, &mut inserted.1, span, end);
+        let before: Vec<_> = points.iter().map(|p| position(&g(p))).collect();
+        let after: Vec<_> = inserted.1.iter().map(|p| position(&g(p))).collect();
+        let continuity = if removal_error(&before, &after) <= tolerance {
+            (knots, points) = removed;
+            Continuity::C1
+        } else {
+            // the neighbouring points may only move by `tolerance` to line up with the end
+            let at = |i: usize| &before[i].0;
+            let leg = |i: usize, j: usize| at(j) - at(i);
+            let (before, after) = (leg(n - 2, n - 1), leg(n - 1, n));
+            let epsilon = T::default_epsilon();
+            match (before.try_normalize(epsilon), after.try_normalize(epsilon)) {
+                (Some(b), Some(a))
+                    if b.dot(&a) > T::zero()
+                        && (&b - &a).norm() * before.norm().min(after.norm()) <= tolerance =>
+                {
+                    Continuity::G1
+                }
+                _ => Continuity::C0,
+            }
+        };
+
+        let points = points.iter().map(g).collect();
+        let joined = Self::with_knots(ControlVec::new(degree, points), knots);
+        (joined, continuity)
+    }
+}
+
+/// Bound on how far apart the curves of the points and weights in `before` and `after` on
+/// the same knots are. Each point of one curve is a weighted average of its points, so it
+/// is no further from the other curve than the points are, plus the change in relative
+/// weight times the spread of the points.
+fn removal_error<D: Dim, T: Real>(before: &[(Vector<D, T>, T)], after: &[(Vector<D, T>, T)]) -> T
+where
+    DefaultAllocator: Allocator<T, D>,
+{
+    let mut moved = T::zero();
+    let mut weights = T::zero();
+    let mut spread = T::zero();
+    for ((p, w), (q, v)) in before.iter().zip(after) {
+        moved = moved.max((p - q).norm());
+        weights = weights.max((*w - *v).abs() / *w);
+        spread = spread.max((q - &after[0].0).norm() * T::cast_from(2));
+    }
+    moved + weights * spread
 }
 
 impl<D: Dim, T: Scalar> Spline<D, T> for BSpline<D, T>
@@ -119,8 +427,7 @@ where
     DefaultAllocator: Allocator<T, D>,
 {
     fn range(&self) -> RangeInclusive<T> {
-        let r = self.knots().range();
-        T::cast_from(*r.start())..=T::cast_from(*r.end())
+        self.knots().range()
     }
 
     fn at(&self, u: T) -> Vector<D, T> {
@@ -129,3 +436,311 @@ where
         })
     }
 }
+
+impl<D: Dim, T: Scalar> SplineDerivative<D, T> for BSpline<D, T>
+where
+    DefaultAllocator: Allocator<T, D>,
+{
+    fn derivative(&self, u: T) -> Vector<D, T> {
+        self.derivative_mapped(u, Clone::clone)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    fn assert_matches<D: Dim>(spline: &impl Spline<D, f64>, part: &impl Spline<D, f64>, period: f64)
+    where
+        DefaultAllocator: Allocator<f64, D>,
+    {
+        let (start, end) = spline.range().into_inner();
+        for u in part.quantize_range(0.05) {
+            let mut at = u;
+            if at > end {
+                at -= period;
+            }
+            assert!((spline.at(at.clamp(start, end)) - part.at(u)).norm() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn it_splits_splines() {
+        let spline = BSpline::new(ControlVec::new(
+            3,
+            vec![
+                Vector2::new(0., 0.),
+                Vector2::new(1., 2.),
+                Vector2::new(2., -1.),
+                Vector2::new(3., 3.),
+                Vector2::new(4., 0.),
+                Vector2::new(5., 1.),
+            ],
+        ));
+        let (before, after) = spline.split_at(4.3);
+        let after = after.unwrap();
+
+        assert_eq!(3.0..=4.3, before.range());
+        assert_eq!(4.3..=6.0, after.range());
+        assert_eq!(before.at(3.), before.control_points()[0]);
+        assert_eq!(after.at(6.), *after.control_points().last().unwrap());
+        assert!((before.at(4.3) - after.at(4.3)).norm() < 1e-12);
+        assert_matches(&spline, &before, 0.);
+        assert_matches(&spline, &after, 0.);
+
+        // splitting at the ends leaves the whole spline
+        for u in [3., 6.] {
+            let (whole, rest) = spline.split_at(u);
+            assert!(rest.is_none());
+            assert_eq!(3.0..=6.0, whole.range());
+            assert_matches(&spline, &whole, 0.);
+        }
+    }
+
+    #[test]
+    fn it_splits_nurbs() {
+        let arc: BSpline<Const<3>, f64> =
+            BSpline::arc(&Vector2::zeros(), &Vector2::x(), &Vector2::y(), 0.5, 0., 4.);
+        let (before, after) = arc.nurbs().split_at(0.4);
+        let after = after.unwrap();
+
+        assert_matches(&arc.nurbs(), &before.nurbs(), 0.);
+        assert_matches(&arc.nurbs(), &after.nurbs(), 0.);
+        for p in after.nurbs().quantize(0.1) {
+            assert!((p.norm() - 0.5).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn it_extracts_subcurves() {
+        let square = BSpline::square();
+        let part = square.subcurve(1.5..=3.25);
+
+        assert_eq!(1.5..=3.25, part.range());
+        assert_eq!(square.at(1.5), part.control_points()[0]);
+        assert_eq!(square.at(3.25), *part.control_points().last().unwrap());
+        assert_matches(&square, &part, 0.);
+
+        let circle = BSpline::circle();
+        let arc = circle.nurbs().subcurve(1.5..=4.);
+        assert_matches(&circle.nurbs(), &arc.nurbs(), 0.);
+    }
+
+    #[test]
+    fn it_elevates_degree() {
+        let circle = BSpline::circle();
+        let elevated = circle.elevate_degree();
+
+        assert_eq!(3, elevated.degree());
+        assert_matches(&circle, &elevated, 0.);
+        let elevated = circle.nurbs().elevate_degree();
+        assert_matches(&circle.nurbs(), &elevated.nurbs(), 0.);
+
+        let wave = BSpline::new(ControlVec::new(
+            2,
+            (0..6)
+                .map(|i| Vector2::new(i as f64, (i % 2) as f64))
+                .collect(),
+        ));
+        let elevated = wave.elevate_degree();
+        assert_eq!(3, elevated.degree());
+        assert_eq!(10, elevated.control_points().len());
+        assert_matches(&wave, &elevated, 0.);
+    }
+
+    #[test]
+    fn it_joins_splines() {
+        let first = BSpline::new(ControlVec::new(
+            2,
+            vec![
+                Vector2::new(0., 0.),
+                Vector2::new(1., 1.),
+                Vector2::new(2., 1.),
+                Vector2::new(3., 0.),
+            ],
+        ));
+        let (before, after) = first.split_at(3.);
+        let after = after.unwrap();
+
+        let (joined, continuity) = before.join(&after, 1e-9).unwrap();
+        assert_eq!(Continuity::C1, continuity);
+        assert_eq!(2, joined.degree());
+        assert_eq!(4, joined.control_points().len());
+        assert_matches(&first, &joined, 0.);
+
+        let mut line = BSpline::new(ControlVec::new(
+            1,
+            vec![Vector2::new(3., 0.), Vector2::new(3., -2.)],
+        ));
+        line.knots_mut().clamp_ends();
+        let end = first.at(4.);
+        line.control_points_mut()[0] = end;
+        let (joined, continuity) = first.join(&line, 1e-9).unwrap();
+        assert_eq!(Continuity::C0, continuity);
+        assert_eq!(2, joined.degree());
+        assert_eq!(2.0..=5.0, joined.range());
+        assert_matches(&first, &joined.subcurve(2.0..=4.0), 0.);
+        assert!((joined.at(5.) - Vector2::new(3., -2.)).norm() < 1e-12);
+
+        assert!(line.join(&first, 1e-9).is_none());
+    }
+
+    #[test]
+    fn it_joins_within_a_tolerance() {
+        let line = |points: [Vector2<f64>; 2]| {
+            let mut line = BSpline::new(ControlVec::new(1, points.to_vec()));
+            line.knots_mut().clamp_ends();
+            line
+        };
+        let first = line([Vector2::new(0., 0.), Vector2::new(1., 0.)]);
+        let bent = line([Vector2::new(1., 0.), Vector2::new(2., 0.01)]);
+        assert_eq!(Continuity::C1, first.join(&bent, 0.02).unwrap().1);
+        assert_eq!(Continuity::C0, first.join(&bent, 0.001).unwrap().1);
+
+        // the same direction at another speed
+        let slow = line([Vector2::new(1., 0.), Vector2::new(2., 0.)]).reparametrized(0.0..=3.0);
+        assert_eq!(Continuity::G1, first.join(&slow, 1e-9).unwrap().1);
+
+        // a vanishing tangent at the end has no direction to compare
+        let mut stopping = BSpline::new(ControlVec::new(
+            2,
+            vec![
+                Vector2::new(0., 0.),
+                Vector2::new(1., 0.),
+                Vector2::new(1., 0.),
+            ],
+        ));
+        stopping.knots_mut().clamp_ends();
+        let straight = line([Vector2::new(1., 0.), Vector2::new(2., 0.)]);
+        assert_eq!(Continuity::C0, stopping.join(&straight, 0.1).unwrap().1);
+    }
+
+    #[test]
+    fn it_joins_corners_of_any_parametrization() {
+        let mut first = BSpline::new(ControlVec::new(
+            2,
+            vec![
+                Vector2::new(0., 0.),
+                Vector2::new(1., 0.),
+                Vector2::new(2., 0.),
+            ],
+        ));
+        first.knots_mut().clamp_ends();
+        let first = first.reparametrized(0.0..=100.0);
+        let mut second = BSpline::new(ControlVec::new(
+            2,
+            vec![
+                Vector2::new(2., 0.),
+                Vector2::new(2.8, 0.6),
+                Vector2::new(3.6, 1.2),
+            ],
+        ));
+        second.knots_mut().clamp_ends();
+        let second = second.reparametrized(0.0..=100.0);
+
+        let (joined, continuity) = first.join(&second, 0.02).unwrap();
+        assert_eq!(Continuity::C0, continuity);
+        assert_matches(&first, &joined.subcurve(first.range()), 0.);
+        let end = *joined.range().end();
+        assert!((joined.at(end) - Vector2::new(3.6, 1.2)).norm() < 1e-12);
+    }
+
+    #[test]
+    fn it_joins_nurbs() {
+        let arc: BSpline<Const<3>, f64> =
+            BSpline::arc(&Vector2::zeros(), &Vector2::x(), &Vector2::y(), 0.5, 0., 4.);
+        let (before, after) = arc.nurbs().split_at(0.4);
+        let mut after = after.unwrap();
+        let assert_same = |joined: &BSpline<Const<3>, f64>| {
+            assert_eq!(arc.range(), joined.range());
+            for i in 0..=20 {
+                let u = i as f64 / 20.;
+                assert!((arc.nurbs().at(u) - joined.nurbs().at(u)).norm() < 1e-9);
+            }
+        };
+
+        let (joined, continuity) = before.nurbs().join(&after.nurbs(), 1e-9).unwrap();
+        assert_eq!(Continuity::C1, continuity);
+        assert_eq!(
+            arc.control_points().len() + 1,
+            joined.control_points().len()
+        );
+        assert_same(&joined);
+
+        // scaling the weights of one part keeps its curve, and is undone to join them
+        for point in after.control_points_mut() {
+            point.z *= 3.;
+        }
+        let (joined, continuity) = before.nurbs().join(&after.nurbs(), 1e-9).unwrap();
+        assert_eq!(Continuity::C1, continuity);
+        assert_eq!(
+            arc.control_points().len() + 1,
+            joined.control_points().len()
+        );
+        assert_same(&joined);
+    }
+
+    #[test]
+    fn it_opens_wrapped_splines() {
+        let square = BSpline::square();
+        let (open, rest) = square.split_at(1.5);
+        assert!(rest.is_none());
+
+        assert_eq!(1.5..=4.5, open.range());
+        assert!((open.at(1.5) - open.at(4.5)).norm() < 1e-12);
+        assert_matches(&square, &open, 3.);
+
+        let circle = BSpline::circle();
+        let (open, _) = circle.nurbs().split_at(2.25);
+        assert_eq!(2.25..=6.25, open.range());
+        assert_matches(&circle.nurbs(), &open.nurbs(), 4.);
+    }
+
+    #[test]
+    fn it_reverses_and_reparametrizes() {
+        let spline = BSpline::with_knots(
+            ControlVec::new(
+                3,
+                vec![
+                    Vector2::new(0., 0.),
+                    Vector2::new(1., 2.),
+                    Vector2::new(2., -1.),
+                    Vector2::new(3., 3.),
+                    Vector2::new(4., 0.),
+                ],
+            ),
+            vec![0., 1., 2., 3., 3.5, 5., 6., 7., 8.],
+        );
+        let reversed = spline.reversed();
+        assert_eq!(3.0..=5.0, reversed.range());
+        for u in spline.quantize_range(0.25) {
+            assert!((spline.at(u) - reversed.at(8. - u)).norm() < 1e-12);
+        }
+
+        let circle = BSpline::circle();
+        let reversed = circle.reversed();
+        let (start, end) = circle.range().into_inner();
+        for i in 0..=20 {
+            let u = start + (end - start) * i as f64 / 20.;
+            let back = start + end - u;
+            assert!((circle.nurbs().at(u) - reversed.nurbs().at(back)).norm() < 1e-12);
+        }
+
+        let normalized = spline.normalized();
+        assert_eq!(0.0..=1.0, normalized.range());
+        let moved = spline.reparametrized(-1.0..=5.0);
+        for i in 0..=10 {
+            let t = i as f64 / 10.;
+            let original = spline.at(3. + 2. * t);
+            assert!((normalized.at(t) - original).norm() < 1e-12);
+            assert!((moved.at(-1. + 6. * t) - original).norm() < 1e-12);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "range must start before it ends")]
+    fn it_rejects_empty_ranges() {
+        BSpline::square().reparametrized(1.0..=1.0);
+    }
+}
@@ -0,0 +1,236 @@
+use crate::control_points::ControlVec;
+use crate::splines::BSpline;
+use crate::types::{Real, Scalar, Vector};
+use alloc::vec::Vec;
+use nalgebra::allocator::Allocator;
+use nalgebra::{DefaultAllocator, DimDiff, DimName, DimSub, U1};
+
+impl<D: DimName + DimSub<U1>, T: Scalar> BSpline<D, T>
+where
+    DimDiff<D, U1>: DimName,
+    DefaultAllocator: Allocator<T, D> + Allocator<T, DimDiff<D, U1>>,
+{
+    /// Weighted spline of the NURBS conic arc from `start` to `end` whose tangents there
+    /// meet at `apex`, over the range zero to one. The arc is part of an ellipse for a
+    /// `weight` below one, a parabola for one and a hyperbola above one.
+    pub fn conic_arc(
+        start: &Vector<DimDiff<D, U1>, T>,
+        apex: &Vector<DimDiff<D, U1>, T>,
+        end: &Vector<DimDiff<D, U1>, T>,
+        weight: T,
+    ) -> Self {
+        let zero = T::cast_from(0);
+        assert!(weight > zero, "weight must be positive");
+        let control = Vec::from([
+            weighted(start, T::one()),
+            weighted(apex, weight),
+            weighted(end, T::one()),
+        ]);
+        let mut knots = alloc::vec![zero; 3];
+        knots.extend([T::one(); 3]);
+        Self::with_knots(ControlVec::new(2, control), knots)
+    }
+}
+
+impl<D: DimName + DimSub<U1>, T: Real> BSpline<D, T>
+where
+    DimDiff<D, U1>: DimName,
+    DefaultAllocator: Allocator<T, D> + Allocator<T, DimDiff<D, U1>>,
+{
+    /// Weighted spline of the NURBS circular arc around `center` of `radius`, in the plane
+    /// of the perpendicular unit vectors `x_axis` and `y_axis`, over the range zero to one.
+    /// The arc goes from `start_angle` to `end_angle` in radians, measured from `x_axis`
+    /// towards `y_axis`, sweeping at most a full turn.
+    pub fn arc(
+        center: &Vector<DimDiff<D, U1>, T>,
+        x_axis: &Vector<DimDiff<D, U1>, T>,
+        y_axis: &Vector<DimDiff<D, U1>, T>,
+        radius: T,
+        start_angle: T,
+        end_angle: T,
+    ) -> Self {
+        Self::elliptical_arc(
+            center,
+            &(x_axis * radius),
+            &(y_axis * radius),
+            start_angle,
+            end_angle,
+        )
+    }
+
+    /// Weighted spline of the NURBS ellipse around `center` with the perpendicular semi
+    /// axes `x_axis` and `y_axis`, starting at `x_axis` over the range zero to one.
+    pub fn ellipse(
+        center: &Vector<DimDiff<D, U1>, T>,
+        x_axis: &Vector<DimDiff<D, U1>, T>,
+        y_axis: &Vector<DimDiff<D, U1>, T>,
+    ) -> Self {
+        Self::elliptical_arc(center, x_axis, y_axis, T::zero(), T::two_pi())
+    }
+
+    /// Weighted spline of the NURBS elliptical arc around `center` with the perpendicular
+    /// semi axes `x_axis` and `y_axis`, over the range zero to one. The arc goes from
+    /// `start_angle` to `end_angle` in radians of the parametric angle, measured from
+    /// `x_axis` towards `y_axis`, sweeping at most a full turn.
+    pub fn elliptical_arc(
+        center: &Vector<DimDiff<D, U1>, T>,
+        x_axis: &Vector<DimDiff<D, U1>, T>,
+        y_axis: &Vector<DimDiff<D, U1>, T>,
+        start_angle: T,
+        end_angle: T,
+    ) -> Self {
+        let sweep = end_angle - start_angle;
+        assert!(
+            sweep > T::zero() && sweep <= T::two_pi() + T::default_epsilon(),
+            "arcs sweep more than nothing and at most a full turn"
+        );
+        // each segment sweeps at most a quarter turn
+        let quarter = T::frac_pi_2();
+        let mut segments = 1;
+        while T::cast_from(segments) * quarter < sweep - T::default_epsilon() {
+            segments += 1;
+        }
+        let step = sweep / T::cast_from(segments);
+        let middle_weight = (step / T::cast_from(2)).cos();
+
+        // the middle control point of each segment is where the end tangents meet
+        let at = |angle: T, distance: T| {
+            center + (x_axis * angle.cos() + y_axis * angle.sin()) * distance
+        };
+        let one = T::cast_from(1);
+        let mut control = Vec::from([weighted(&at(start_angle, one), one)]);
+        let mut knots = alloc::vec![T::zero(); 3];
+        for i in 1..=segments {
+            let angle = start_angle + step * T::cast_from(i);
+            let middle = angle - step / T::cast_from(2);
+            control.push(weighted(&at(middle, one / middle_weight), middle_weight));
+            control.push(weighted(&at(angle, one), one));
+            let knot = T::cast_from(i) / T::cast_from(segments);
+            knots.extend([knot; 2]);
+        }
+        knots.push(one);
+        Self::with_knots(ControlVec::new(2, control), knots)
+    }
+}
+
+/// NURBS control point of `point` with `weight` appended.
+fn weighted<D: DimName + DimSub<U1>, T: Scalar>(
+    point: &Vector<DimDiff<D, U1>, T>,
+    weight: T,
+) -> Vector<D, T>
+where
+    DimDiff<D, U1>: DimName,
+    DefaultAllocator: Allocator<T, D> + Allocator<T, DimDiff<D, U1>>,
+{
+    Vector::<D, T>::from_iterator(point.iter().copied().chain([weight]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::splines::Spline;
+    use core::f64::consts::{FRAC_PI_2, PI};
+    use fixed::types::extra::U16;
+    use fixed::FixedI64;
+    use nalgebra::{Const, Vector2, Vector3};
+
+    #[test]
+    fn it_makes_arcs() {
+        let center = Vector2::new(1., -2.);
+        for (end, count) in [
+            (0.5, 3),
+            (FRAC_PI_2, 3),
+            (2., 5),
+            (PI, 5),
+            (4., 7),
+            (2. * PI, 9),
+        ] {
+            let arc: BSpline<Const<3>, f64> =
+                BSpline::arc(&center, &Vector2::x(), &Vector2::y(), 2., 0.3, 0.3 + end);
+            assert_eq!(count, arc.control_points().len());
+            assert_eq!(0.0..=1.0, arc.range());
+            let nurbs = arc.nurbs();
+            for i in 0..=50 {
+                let p = nurbs.at(i as f64 / 50.) - center;
+                assert!((p.norm() - 2.).abs() < 1e-12);
+            }
+            let start = nurbs.at(0.) - center;
+            let finish = nurbs.at(1.) - center;
+            assert!((start.y.atan2(start.x) - 0.3).abs() < 1e-12);
+            let turned = finish.y.atan2(finish.x) - 0.3 - end;
+            assert!(((turned / (2. * PI)).round() * 2. * PI - turned).abs() < 1e-12);
+        }
+
+        // arcs lie in the plane of their axes
+        let (x, y) = (
+            Vector3::new(1., 1., 0.).normalize(),
+            Vector3::new(0., 0., 1.),
+        );
+        let arc: BSpline<Const<4>, f64> = BSpline::arc(&Vector3::zeros(), &x, &y, 3., -1., 2.);
+        let normal = x.cross(&y);
+        for i in 0..=20 {
+            let p = arc.nurbs().at(i as f64 / 20.);
+            assert!((p.norm() - 3.).abs() < 1e-12);
+            assert!(p.dot(&normal).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn it_makes_ellipses() {
+        let ellipse: BSpline<Const<3>, f64> = BSpline::ellipse(
+            &Vector2::zeros(),
+            &Vector2::new(3., 0.),
+            &Vector2::new(0., 1.),
+        );
+        let nurbs = ellipse.nurbs();
+        assert!((nurbs.at(0.) - nurbs.at(1.)).norm() < 1e-12);
+        for i in 0..=40 {
+            let p = nurbs.at(i as f64 / 40.);
+            assert!(((p.x / 3.).powi(2) + p.y.powi(2) - 1.).abs() < 1e-12);
+        }
+
+        let arc: BSpline<Const<3>, f64> = BSpline::elliptical_arc(
+            &Vector2::zeros(),
+            &Vector2::new(3., 0.),
+            &Vector2::new(0., 1.),
+            0.,
+            PI,
+        );
+        assert!((arc.nurbs().at(1.) - Vector2::new(-3., 0.)).norm() < 1e-12);
+        assert!((arc.nurbs().at(0.5) - Vector2::new(0., 1.)).norm() < 1e-12);
+    }
+
+    #[test]
+    fn it_makes_conic_arcs() {
+        let (start, apex, end) = (
+            Vector2::new(-1., 0.),
+            Vector2::new(0., 1.),
+            Vector2::new(1., 0.),
+        );
+        for weight in [0.2, 1. / 2f64.sqrt(), 1., 3.] {
+            let conic: BSpline<Const<3>, f64> = BSpline::conic_arc(&start, &apex, &end, weight);
+            // the shoulder point lies between the middle of the chord and the apex
+            let shoulder = (start + end) / 2. / (1. + weight) + apex * weight / (1. + weight);
+            assert!((conic.nurbs().at(0.5) - shoulder).norm() < 1e-12);
+        }
+
+        // a weight of the cosine of half the sweep gives a circular arc
+        let conic: BSpline<Const<3>, f64> =
+            BSpline::conic_arc(&start, &apex, &end, 1. / 2f64.sqrt());
+        for i in 0..=10 {
+            let p = conic.nurbs().at(i as f64 / 10.);
+            assert!(((p - Vector2::new(0., -1.)).norm() - 2f64.sqrt()).abs() < 1e-12);
+        }
+
+        // conics need no real valued operations
+        type Fixed = FixedI64<U16>;
+        let f = |x: f64| Fixed::from_num(x);
+        let conic: BSpline<Const<3>, Fixed> = BSpline::conic_arc(
+            &Vector2::new(f(0.), f(0.)),
+            &Vector2::new(f(1.), f(1.)),
+            &Vector2::new(f(2.), f(0.)),
+            f(1.),
+        );
+        assert_eq!(Vector2::new(f(1.), f(0.5)), conic.nurbs().at(f(0.5)));
+    }
+}
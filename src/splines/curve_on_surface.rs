@@ -0,0 +1,143 @@
+use crate::splines::{Spline, SplineDerivative};
+use crate::surfaces::{Surface, SurfaceDerivatives, UV};
+use crate::types::{Scalar, Vector};
+use core::ops::RangeInclusive;
+use nalgebra::allocator::Allocator;
+use nalgebra::{Const, DefaultAllocator, Dim};
+
+/// Curve lying on a surface, given by a 2D curve in the uv space of the surface.
+#[derive(Debug)]
+pub struct CurveOnSurface<'a, C, S> {
+    curve: &'a C,
+    surface: &'a S,
+}
+
+impl<'a, C, S> CurveOnSurface<'a, C, S> {
+    /// Place the uv `curve` on `surface`.
+    pub fn new(curve: &'a C, surface: &'a S) -> Self {
+        Self { curve, surface }
+    }
+
+    /// The curve in uv space.
+    pub fn curve(&self) -> &'a C {
+        self.curve
+    }
+
+    /// The surface the curve lies on.
+    pub fn surface(&self) -> &'a S {
+        self.surface
+    }
+
+    /// Coordinate on the surface at position `t` along the curve.
+    pub fn uv<T: Scalar>(&self, t: T) -> UV<T>
+    where
+        C: Spline<Const<2>, T>,
+    {
+        let uv = self.curve.at(t);
+        (uv.x, uv.y)
+    }
+
+    /// If the curve is within the ranges of the surface at position `t`.
+    pub fn in_domain<D: Dim, T: Scalar>(&self, t: T) -> bool
+    where
+        C: Spline<Const<2>, T>,
+        S: Surface<D, T>,
+        DefaultAllocator: Allocator<T, D>,
+    {
+        let (u, v) = self.uv(t);
+        self.surface.u_range().contains(&u) && self.surface.v_range().contains(&v)
+    }
+}
+
+impl<D: Dim, T: Scalar, C, S> Spline<D, T> for CurveOnSurface<'_, C, S>
+where
+    C: Spline<Const<2>, T>,
+    S: Surface<D, T>,
+    DefaultAllocator: Allocator<T, D>,
+{
+    fn range(&self) -> RangeInclusive<T> {
+        self.curve.range()
+    }
+
+    fn at(&self, t: T) -> Vector<D, T> {
+        assert!(self.in_domain(t), "uv out of range");
+        self.surface.at(self.uv(t))
+    }
+}
+
+impl<D: Dim, T: Scalar, C, S> SplineDerivative<D, T> for CurveOnSurface<'_, C, S>
+where
+    C: SplineDerivative<Const<2>, T>,
+    S: SurfaceDerivatives<D, T>,
+    DefaultAllocator: Allocator<T, D>,
+{
+    fn derivative(&self, t: T) -> Vector<D, T> {
+        assert!(self.in_domain(t), "uv out of range");
+        let (du, dv) = self.surface.derivatives(self.uv(t));
+        let duv = self.curve.derivative(t);
+        du * duv.x + dv * duv.y
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::control_points::{ControlGrid, ControlVec};
+    use crate::splines::BSpline;
+    use crate::surfaces::tests::bumpy_points;
+    use crate::surfaces::BSurface;
+    use alloc::vec;
+    use nalgebra::{Vector2, Vector3};
+
+    #[test]
+    fn it_follows_the_surface() {
+        let surface = BSurface::new(ControlGrid::new(2, 4, bumpy_points(4)));
+        let nurbs = surface.nurbs();
+        let curve = BSpline::new(ControlVec::new(
+            2,
+            vec![
+                Vector2::new(2.1, 2.5),
+                Vector2::new(3.5, 2.0),
+                Vector2::new(2.5, 3.9),
+                Vector2::new(3.9, 3.5),
+            ],
+        ));
+        let on_surface = CurveOnSurface::new(&curve, &nurbs);
+
+        let h = 1e-6;
+        for i in 0..=20 {
+            let t = 2. + i as f64 / 10.;
+            assert!(on_surface.in_domain(t));
+            let uv = curve.at(t);
+            assert!((on_surface.at(t) - nurbs.at((uv.x, uv.y))).norm() < 1e-12);
+
+            let t = t.min(4. - h);
+            let difference: Vector3<f64> = (on_surface.at(t + h) - on_surface.at(t)) / h;
+            assert!((on_surface.derivative(t) - difference).norm() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn it_checks_the_domain() {
+        let surface = BSurface::new(ControlGrid::new(
+            1,
+            2,
+            vec![
+                Vector3::new(0., 0., 0.),
+                Vector3::new(1., 0., 0.),
+                Vector3::new(0., 1., 0.),
+                Vector3::new(1., 1., 1.),
+            ],
+        ));
+        let mut curve = BSpline::new(ControlVec::new(
+            1,
+            vec![Vector2::new(1.5, 1.5), Vector2::new(2.5, 1.5)],
+        ));
+        curve.knots_mut().clamp_ends();
+        let on_surface = CurveOnSurface::new(&curve, &surface);
+
+        assert!(on_surface.in_domain(1.25));
+        assert!(!on_surface.in_domain(1.75));
+        assert_eq!(Vector3::new(0.75, 0.5, 0.375), on_surface.at(1.25));
+    }
+}
@@ -0,0 +1,370 @@
+use crate::algorithms::{averaged_knots, basis_derivatives, basis_functions, collocation, solve};
+use crate::control_points::ControlVec;
+use crate::knots::Knots;
+use crate::splines::BSpline;
+use crate::types::{Real, Vector};
+use alloc::vec::Vec;
+use nalgebra::allocator::Allocator;
+use nalgebra::{DMatrix, DefaultAllocator, Dim};
+
+/// How data points are spread over the range of a spline fitted through them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Parametrization {
+    /// Evenly spaced regardless of the points.
+    Uniform,
+    /// Spaced by the distances between the points.
+    ChordLength,
+    /// Spaced by the square roots of the distances between the points, which avoids
+    /// overshooting around sharp turns.
+    Centripetal,
+}
+
+impl Parametrization {
+    /// Parameters of the points from zero to one. When `closed` the parameter of the
+    /// first point after going around, one, is also included.
+    pub(crate) fn parameters<D: Dim, T: Real>(
+        &self,
+        points: &[Vector<D, T>],
+        closed: bool,
+    ) -> Vec<T>
+    where
+        DefaultAllocator: Allocator<T, D>,
+    {
+        let gaps = points.len() - 1 + usize::from(closed);
+        let mut lengths: Vec<T> = (0..gaps)
+            .map(|i| {
+                let distance = (&points[(i + 1) % points.len()] - &points[i]).norm();
+                match self {
+                    Self::Uniform => T::cast_from(1),
+                    Self::ChordLength => distance,
+                    Self::Centripetal => distance.sqrt(),
+                }
+            })
+            .collect();
+        let mut total = lengths.iter().fold(T::zero(), |a, &b| a + b);
+        if total == T::zero() {
+            lengths.fill(T::cast_from(1));
+            total = T::cast_from(gaps);
+        }
+
+        let mut parameters = Vec::with_capacity(gaps + 1);
+        let mut sum = T::zero();
+        parameters.push(sum);
+        for length in &lengths[..gaps - 1] {
+            sum += *length;
+            parameters.push(sum / total);
+        }
+        parameters.push(T::cast_from(1));
+        parameters
+    }
+}
+
+impl<D: Dim, T: Real> BSpline<D, T>
+where
+    DefaultAllocator: Allocator<T, D>,
+{
+    /// Spline of `degree` passing through every point in order over the range zero to one,
+    /// with the points spread out by `parametrization` and knots averaged between them.
+    /// `None` if the points cannot be interpolated, such as when repeated points get the
+    /// same parameter.
+    pub fn interpolate(
+        points: &[Vector<D, T>],
+        degree: usize,
+        parametrization: Parametrization,
+    ) -> Option<Self> {
+        Interpolation::new(points)
+            .degree(degree)
+            .parametrization(parametrization)
+            .build()
+    }
+
+    /// Wrapping spline of `degree` passing through every point in order and back to the
+    /// first, going once around over a range of length one. The points are spread out by
+    /// `parametrization` and the knots are averaged between them.
+    /// `None` if the points cannot be interpolated, such as when repeated points get the
+    /// same parameter.
+    pub fn interpolate_closed(
+        points: &[Vector<D, T>],
+        degree: usize,
+        parametrization: Parametrization,
+    ) -> Option<Self> {
+        assert!(degree > 0, "cannot interpolate with degree 0");
+        assert!(points.len() > degree, "not enough points for the degree");
+        let parameters = parametrization.parameters(points, true);
+        let knots = periodic_knots(&parameters, degree);
+
+        let n = points.len();
+        let mut matrix = DMatrix::zeros(n, n);
+        let k = Knots::new(degree, knots.as_slice());
+        for (row, &u) in parameters[..n].iter().enumerate() {
+            let u = wrap(u, k.range().into_inner());
+            let span = k.find_span(u);
+            for (i, value) in basis_functions(u, span, degree, &k).into_iter().enumerate() {
+                matrix[(row, (span - degree + i) % n)] += value;
+            }
+        }
+
+        let control = solve(matrix, points)?;
+        let mut control = ControlVec::new(degree, control);
+        control.set_wrapping(true);
+        Some(Self::with_knots(control, knots))
+    }
+}
+
+/// Builder of a spline through points which can also be given the derivatives the
+/// spline has at any of them, such as the tangents at the ends or, Hermite style, at
+/// every point. Derivatives are with respect to the range of the spline, zero to one.
+#[derive(Debug, Clone)]
+pub struct Interpolation<'a, D: Dim, T: Real>
+where
+    DefaultAllocator: Allocator<T, D>,
+{
+    points: &'a [Vector<D, T>],
+    degree: usize,
+    parametrization: Parametrization,
+    derivatives: Vec<(usize, Vector<D, T>)>,
+}
+
+impl<'a, D: Dim, T: Real> Interpolation<'a, D, T>
+where
+    DefaultAllocator: Allocator<T, D>,
+{
+    /// Interpolate `points` with a cubic spline and chord length parametrization.
+    pub fn new(points: &'a [Vector<D, T>]) -> Self {
+        Self {
+            points,
+            degree: 3,
+            parametrization: Parametrization::ChordLength,
+            derivatives: Vec::new(),
+        }
+    }
+
+    /// Set the degree of the spline.
+    pub fn degree(mut self, degree: usize) -> Self {
+        self.degree = degree;
+        self
+    }
+
+    /// Set how the points are spread over the range of the spline.
+    pub fn parametrization(mut self, parametrization: Parametrization) -> Self {
+        self.parametrization = parametrization;
+        self
+    }
+
+    /// Require the spline to have `derivative` at the point at `index`.
+    pub fn derivative(mut self, index: usize, derivative: Vector<D, T>) -> Self {
+        assert!(index < self.points.len(), "index out of range");
+        self.derivatives.retain(|(i, _)| *i != index);
+        self.derivatives.push((index, derivative));
+        self
+    }
+
+    /// Require the spline to have `derivative` at the first point.
+    pub fn start_derivative(self, derivative: Vector<D, T>) -> Self {
+        self.derivative(0, derivative)
+    }
+
+    /// Require the spline to have `derivative` at the last point.
+    pub fn end_derivative(self, derivative: Vector<D, T>) -> Self {
+        let last = self.points.len() - 1;
+        self.derivative(last, derivative)
+    }
+
+    /// Spline through the points with the required derivatives.
+    /// `None` if the points cannot be interpolated, such as when repeated points get the
+    /// same parameter.
+    pub fn build(mut self) -> Option<BSpline<D, T>> {
+        let degree = self.degree;
+        assert!(degree > 0, "cannot interpolate with degree 0");
+        let count = self.points.len() + self.derivatives.len();
+        assert!(count > degree, "not enough points for the degree");
+        self.derivatives.sort_by_key(|(i, _)| *i);
+
+        // every point with a derivative takes two control points, so its parameter is
+        // counted twice when placing the knots
+        let parameters = self.parametrization.parameters(self.points, false);
+        let mut repeated = Vec::with_capacity(count);
+        let mut constrained = self.derivatives.iter().map(|(i, _)| *i).peekable();
+        for (i, &u) in parameters.iter().enumerate() {
+            repeated.push(u);
+            if constrained.next_if_eq(&i).is_some() {
+                repeated.push(u);
+            }
+        }
+        let knots = averaged_knots(&repeated, degree);
+
+        // rows for the points followed by rows for the derivatives
+        let mut matrix =
+            collocation(&parameters, &knots, degree).resize_vertically(count, T::zero());
+        let mut values = Vec::from(self.points);
+        let k = Knots::new(degree, knots.as_slice());
+        for (row, (i, derivative)) in self.derivatives.into_iter().enumerate() {
+            let u = parameters[i];
+            let span = k.find_span(u);
+            let basis = basis_derivatives(u, span, degree, &k);
+            for (i, value) in basis.into_iter().enumerate() {
+                matrix[(self.points.len() + row, span - degree + i)] = value;
+            }
+            values.push(derivative);
+        }
+
+        let control = solve(matrix, &values)?;
+        Some(BSpline::with_knots(ControlVec::new(degree, control), knots))
+    }
+}
+
+/// Knots of a wrapping spline which repeat with a period of one, each the average of
+/// `degree` consecutive parameters of the closed loop continued around.
+fn periodic_knots<T: Real>(parameters: &[T], degree: usize) -> Vec<T> {
+    let n = parameters.len() as isize - 1;
+    let parameter = |k: isize| {
+        let periods = k.div_euclid(n);
+        let value = parameters[k.rem_euclid(n) as usize];
+        if periods >= 0 {
+            value + T::cast_from(periods as usize)
+        } else {
+            value - T::cast_from(-periods as usize)
+        }
+    };
+    let p = degree as isize;
+    (0..n + 2 * p + 1)
+        .map(|j| {
+            let first = j - p - p / 2;
+            let sum = (first..first + p)
+                .map(parameter)
+                .fold(T::zero(), |a, b| a + b);
+            sum / T::cast_from(degree)
+        })
+        .collect()
+}
+
+/// Move `u` by whole periods of one into `start..end`.
+fn wrap<T: Real>(u: T, (start, _): (T, T)) -> T {
+    u - (u - start).floor()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::splines::{Spline, SplineDerivative};
+    use alloc::vec;
+    use nalgebra::{Vector2, Vector3};
+
+    fn data() -> Vec<Vector2<f64>> {
+        vec![
+            Vector2::new(0., 0.),
+            Vector2::new(1., 2.),
+            Vector2::new(3., 2.5),
+            Vector2::new(4., 0.),
+            Vector2::new(6., -1.),
+            Vector2::new(6.5, 3.),
+        ]
+    }
+
+    #[test]
+    fn it_interpolates_points() {
+        let points = data();
+        for parametrization in [
+            Parametrization::Uniform,
+            Parametrization::ChordLength,
+            Parametrization::Centripetal,
+        ] {
+            for degree in 1..=3 {
+                let spline = BSpline::interpolate(&points, degree, parametrization).unwrap();
+                assert_eq!(0.0..=1.0, spline.range());
+                assert_eq!(points.len(), spline.control_points().len());
+                let parameters = parametrization.parameters(&points, false);
+                for (point, u) in points.iter().zip(parameters) {
+                    assert!((spline.at(u) - point).norm() < 1e-9);
+                }
+            }
+        }
+
+        let chords = Parametrization::ChordLength.parameters(&points, false);
+        let total: f64 = points.windows(2).map(|w| (w[1] - w[0]).norm()).sum();
+        assert!((chords[1] - 5f64.sqrt() / total).abs() < 1e-12);
+        let uniform = Parametrization::Uniform.parameters(&points, false);
+        for (i, u) in uniform.into_iter().enumerate() {
+            assert!((u - i as f64 / 5.).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn it_interpolates_closed_loops() {
+        let points: Vec<_> = (0..7)
+            .map(|i| {
+                let angle = i as f64 * 0.9 + (i % 2) as f64 * 0.2;
+                Vector3::new(angle.cos(), angle.sin(), (i % 3) as f64 / 4.)
+            })
+            .collect();
+        for degree in 1..=4 {
+            let spline =
+                BSpline::interpolate_closed(&points, degree, Parametrization::Centripetal).unwrap();
+            let (start, end) = spline.range().into_inner();
+            assert!((end - start - 1.).abs() < 1e-12);
+            assert!(spline.control_vec().wrapping());
+
+            let parameters = Parametrization::Centripetal.parameters(&points, true);
+            for (point, &u) in points.iter().zip(&parameters) {
+                let u = wrap(u, (start, end));
+                assert!((spline.at(u) - point).norm() < 1e-9);
+            }
+            assert!((spline.at(start) - spline.at(end)).norm() < 1e-9);
+            if degree > 1 {
+                let seam = spline.derivative(start) - spline.derivative(end);
+                assert!(seam.norm() < 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn it_rejects_repeated_points() {
+        let points = [
+            Vector2::new(0f64, 0.),
+            Vector2::new(1., 1.),
+            Vector2::new(1., 1.),
+        ];
+        assert!(BSpline::interpolate(&points, 2, Parametrization::ChordLength).is_none());
+    }
+
+    #[test]
+    fn it_interpolates_derivatives() {
+        let points = data();
+        let start = Vector2::new(10., 0.);
+        let end = Vector2::new(0., -10.);
+        let spline = Interpolation::new(&points)
+            .start_derivative(start)
+            .end_derivative(end)
+            .build()
+            .unwrap();
+
+        assert_eq!(points.len() + 2, spline.control_points().len());
+        assert!((spline.derivative(0.) - start).norm() < 1e-9);
+        assert!((spline.derivative(1.) - end).norm() < 1e-9);
+        let parameters = Parametrization::ChordLength.parameters(&points, false);
+        for (point, &u) in points.iter().zip(&parameters) {
+            assert!((spline.at(u) - point).norm() < 1e-9);
+        }
+
+        // hermite style, with a derivative at every point
+        let tangents: Vec<_> = points
+            .iter()
+            .enumerate()
+            .map(|(i, _)| Vector2::new(1., (i as f64).sin()) * 8.)
+            .collect();
+        for degree in [2, 3, 5] {
+            let mut hermite = Interpolation::new(&points)
+                .degree(degree)
+                .parametrization(Parametrization::Centripetal);
+            for (i, tangent) in tangents.iter().enumerate() {
+                hermite = hermite.derivative(i, *tangent);
+            }
+            let spline = hermite.build().unwrap();
+            let parameters = Parametrization::Centripetal.parameters(&points, false);
+            for i in 0..points.len() {
+                assert!((spline.at(parameters[i]) - points[i]).norm() < 1e-9);
+                assert!((spline.derivative(parameters[i]) - tangents[i]).norm() < 1e-9);
+            }
+        }
+    }
+}
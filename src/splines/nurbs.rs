@@ -1,7 +1,7 @@
-use crate::algorithms::cox_de_boor_u;
+use crate::algorithms::{cox_de_boor_u, project_derivative, unweight, weight};
 use crate::splines::BSpline;
-use crate::splines::Spline;
-use crate::types::{Scalar, Vector};
+use crate::splines::{Continuity, Spline, SplineDerivative};
+use crate::types::{Real, Scalar, Vector};
 use core::ops::RangeInclusive;
 use nalgebra::allocator::Allocator;
 use nalgebra::{Const, DefaultAllocator, Dim, DimDiff, DimName, DimSub, U1};
@@ -22,6 +22,61 @@ where
     pub(crate) fn new(spline: &'a BSpline<D, T>) -> Self {
         Self { spline }
     }
+
+    /// The weighted spline.
+    pub(crate) fn spline(&self) -> &'a BSpline<D, T> {
+        self.spline
+    }
+
+    /// Split at `u` into the weighted splines of the NURBS before and after it, cutting a
+    /// wrapping NURBS open at `u` instead, as for [`BSpline::split_at`].
+    pub fn split_at(&self, u: T) -> (BSpline<D, T>, Option<BSpline<D, T>>) {
+        self.spline.split_mapped(u, weight, unweight)
+    }
+
+    /// Weighted spline of the NURBS covering `range`, which lies within the range of the NURBS.
+    pub fn subcurve(&self, range: RangeInclusive<T>) -> BSpline<D, T> {
+        self.spline.subcurve_mapped(range, weight, unweight)
+    }
+
+    /// Weighted spline of the same NURBS of one degree higher.
+    pub fn elevate_degree(&self) -> BSpline<D, T> {
+        self.spline.elevate_mapped(weight, unweight)
+    }
+}
+
+impl<D: Dim + DimSub<U1>, T: Real> NURBSpline<'_, D, T>
+where
+    <D as DimSub<Const<1>>>::Output: DimName,
+    DefaultAllocator: Allocator<T, D>,
+    DefaultAllocator: Allocator<T, <D as DimSub<Const<1>>>::Output>,
+    <DefaultAllocator as Allocator<T, <D as DimSub<Const<1>>>::Output>>::Buffer: Default,
+{
+    /// Weighted spline of this NURBS followed by `other`, as for [`BSpline::join`]. The
+    /// weights of `other` are scaled to match where they meet, which keeps its curve the
+    /// same.
+    pub fn join(
+        &self,
+        other: &NURBSpline<'_, D, T>,
+        tolerance: T,
+    ) -> Option<(BSpline<D, T>, Continuity)> {
+        let (end, start) = (*self.range().end(), *other.range().start());
+        if (self.at(end) - other.at(start)).norm() > tolerance {
+            return None;
+        }
+        let (first, mut second) = self.spline.matched(other.spline, weight);
+        let w = |p: &Vector<D, T>| p[p.len() - 1];
+        let last = &first.control_points()[first.control_points().len() - 1];
+        let scale = w(last) / w(&second.control_points()[0]);
+        for point in second.control_points_mut() {
+            *point *= scale;
+        }
+        Some(BSpline::joined(first, second, tolerance, unweight, |p| {
+            let mut point = p.clone();
+            point[p.len() - 1] = T::zero();
+            (point, w(p))
+        }))
+    }
 }
 
 impl<D: Dim + DimSub<U1>, T: Scalar> Spline<DimDiff<D, U1>, T> for NURBSpline<'_, D, T>
@@ -51,3 +106,19 @@ where
         lower
     }
 }
+
+impl<D: Dim + DimSub<U1>, T: Scalar> SplineDerivative<DimDiff<D, U1>, T> for NURBSpline<'_, D, T>
+where
+    <D as DimSub<Const<1>>>::Output: DimName,
+    DefaultAllocator: Allocator<T, D>,
+    DefaultAllocator: Allocator<T, <D as DimSub<Const<1>>>::Output>,
+    <DefaultAllocator as Allocator<T, <D as DimSub<Const<1>>>::Output>>::Buffer: Default,
+{
+    fn derivative(&self, u: T) -> Vector<DimDiff<D, U1>, T> {
+        let point = cox_de_boor_u(u, self.spline.degree(), &self.spline.knots(), |i| {
+            weight(&self.spline.control_vec()[i])
+        });
+        let derivative = self.spline.derivative_mapped(u, weight);
+        project_derivative(&point, &derivative)
+    }
+}
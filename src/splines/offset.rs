@@ -0,0 +1,295 @@
+use crate::algorithms::distance_to_segment;
+use crate::control_points::ControlVec;
+use crate::splines::{BSpline, NURBSpline, SplineDerivative};
+use crate::types::Real;
+use alloc::vec::Vec;
+use nalgebra::{Const, Vector2};
+
+/// Initial number of samples within each Bezier segment.
+const SAMPLES: usize = 8;
+/// Maximum number of times a sampled interval is halved.
+const MAX_DEPTH: usize = 16;
+/// Longest loop cut out, as a multiple of the offset distance. Longer loops are crossings
+/// of distant parts of the offset rather than loops of a tight bend, and are kept.
+const MAX_LOOP: usize = 8;
+
+/// Point on the offset at a parameter, with the error of the segment of the polyline
+/// ending there.
+type Sample<T> = (T, Vector2<T>, T);
+
+impl<T: Real> BSpline<Const<2>, T> {
+    /// Curve offset by `distance` to the left of its direction, approximated by a linear
+    /// spline. Intervals are halved where the offset is further than `tolerance` from the
+    /// chord, measured at the middle of each interval. Local loops formed where the distance
+    /// exceeds the radius of curvature are cut out at the point where the offset crosses
+    /// itself, while crossings of parts of the offset further apart along it are kept.
+    /// Returned with the largest error measured, which is above `tolerance` if it is not
+    /// reached within a fixed number of halvings, or `None` if the curve has no direction
+    /// somewhere, such as when all its control points are the same.
+    pub fn offset(&self, distance: T, tolerance: T) -> Option<(Self, T)> {
+        offset(self, &breaks(self), distance, tolerance)
+    }
+}
+
+impl<T: Real> NURBSpline<'_, Const<3>, T> {
+    /// Curve offset by `distance` to the left of its direction, approximated by a linear
+    /// spline as for [`BSpline::offset`].
+    pub fn offset(&self, distance: T, tolerance: T) -> Option<(BSpline<Const<2>, T>, T)> {
+        offset(self, &breaks(self.spline()), distance, tolerance)
+    }
+}
+
+/// Ends of the Bezier segments of a spline, where its derivatives may jump.
+fn breaks<const D: usize, T: Real>(spline: &BSpline<Const<D>, T>) -> Vec<T> {
+    let segments = spline.bezier_segments(Clone::clone);
+    let mut breaks: Vec<_> = segments.iter().map(|s| s.start).collect();
+    breaks.extend(segments.last().map(|s| s.end));
+    breaks
+}
+
+fn offset<T: Real>(
+    curve: &impl SplineDerivative<Const<2>, T>,
+    breaks: &[T],
+    distance: T,
+    tolerance: T,
+) -> Option<(BSpline<Const<2>, T>, T)> {
+    let point = |u: T| Some(curve.at(u) + normal(curve, u)? * distance);
+
+    // sample each segment then halve intervals until the chords are close enough
+    let mut samples: Vec<Sample<T>> = Vec::new();
+    for span in breaks.windows(2) {
+        let (start, end) = (span[0], span[1]);
+        if samples.is_empty() {
+            samples.push((start, point(start)?, T::zero()));
+        }
+        for i in 1..=SAMPLES {
+            let u = start + (end - start) * T::cast_from(i) / T::cast_from(SAMPLES);
+            let (v, last, _) = samples[samples.len() - 1];
+            refine(
+                &point,
+                (v, last),
+                (u, point(u)?),
+                tolerance,
+                0,
+                &mut samples,
+            )?;
+        }
+    }
+
+    let window = distance.abs() * T::cast_from(MAX_LOOP);
+    let samples = remove_loops(&point, samples, window, tolerance)?;
+    let error = samples.iter().fold(T::zero(), |error, s| error.max(s.2));
+    let (knots, points): (Vec<_>, Vec<_>) = samples.into_iter().map(|(u, p, _)| (u, p)).unzip();
+    let mut clamped = Vec::with_capacity(knots.len() + 2);
+    clamped.push(knots[0]);
+    clamped.extend_from_slice(&knots);
+    clamped.push(knots[knots.len() - 1]);
+    Some((
+        BSpline::with_knots(ControlVec::new(1, points), clamped),
+        error,
+    ))
+}
+
+/// Unit normal to the left of the direction of `curve` at `u`. Where the derivative
+/// vanishes the direction between nearby points is used instead, and if they are the
+/// same there is no normal.
+fn normal<T: Real>(curve: &impl SplineDerivative<Const<2>, T>, u: T) -> Option<Vector2<T>> {
+    let epsilon = T::default_epsilon();
+    let tangent = match curve.derivative(u).try_normalize(epsilon.sqrt()) {
+        Some(tangent) => tangent,
+        None => {
+            let (start, end) = curve.range().into_inner();
+            let h = (end - start) * epsilon.sqrt();
+            (curve.at((u + h).min(end)) - curve.at((u - h).max(start))).try_normalize(epsilon)?
+        }
+    };
+    Some(Vector2::new(-tangent.y, tangent.x))
+}
+
+/// Push samples up to and including `end`, halving the interval from `start` while
+/// its middle is further than `tolerance` from the chord.
+fn refine<T: Real>(
+    point: &impl Fn(T) -> Option<Vector2<T>>,
+    start: (T, Vector2<T>),
+    end: (T, Vector2<T>),
+    tolerance: T,
+    depth: usize,
+    samples: &mut Vec<Sample<T>>,
+) -> Option<()> {
+    let u = (start.0 + end.0) / T::cast_from(2);
+    let middle = (u, point(u)?);
+    let error = distance_to_segment(&middle.1, &start.1, &end.1);
+    if depth < MAX_DEPTH && error > tolerance {
+        refine(point, start, middle, tolerance, depth + 1, samples)?;
+        refine(point, middle, end, tolerance, depth + 1, samples)
+    } else {
+        samples.push((end.0, end.1, error));
+        Some(())
+    }
+}
+
+/// Cut out the loops of a polyline no longer than `window`, joining the segments which
+/// cross at their crossing. The joined segments are refined again so that they stay
+/// within `tolerance` of the offset.
+fn remove_loops<T: Real>(
+    point: &impl Fn(T) -> Option<Vector2<T>>,
+    mut samples: Vec<Sample<T>>,
+    window: T,
+    tolerance: T,
+) -> Option<Vec<Sample<T>>> {
+    let mut i = 0;
+    while i + 1 < samples.len() {
+        let (a, b) = (samples[i].1, samples[i + 1].1);
+        let mut length = T::zero();
+        let mut found = None;
+        for j in i + 2..samples.len() - 1 {
+            let (c, d) = (samples[j].1, samples[j + 1].1);
+            length += (c - samples[j - 1].1).norm();
+            if length > window {
+                break;
+            }
+            if let Some((s, t)) = crossing(&a, &b, &c, &d) {
+                found = Some((j, s, t));
+                break;
+            }
+        }
+
+        if let Some((j, s, t)) = found {
+            // the end of a closed curve meeting its start is not a loop
+            let closed = i == 0
+                && j + 2 == samples.len()
+                && (samples[j + 1].1 - a).norm() <= tolerance
+                && (b - a).norm() * s <= tolerance;
+            if !closed {
+                let (start, end) = (samples[i], samples[j + 1]);
+                let (start, end) = ((start.0, start.1), (end.0, end.1));
+                let at = |k: usize, x: T| samples[k].0 + (samples[k + 1].0 - samples[k].0) * x;
+                let (before, after) = (at(i, s), at(j, t));
+                let joint = a + (b - a) * s;
+
+                // the joint ends the part before the loop and starts the part after it
+                let mut joined = Vec::new();
+                refine(point, start, (before, joint), tolerance, 0, &mut joined)?;
+                joined.last_mut().unwrap().0 = after;
+                refine(point, (after, joint), end, tolerance, 0, &mut joined)?;
+                samples.splice(i + 1..=j + 1, joined);
+            }
+        }
+        i += 1;
+    }
+    Some(samples)
+}
+
+/// Where segments `ab` and `cd` cross, as the fractions along each of them.
+fn crossing<T: Real>(
+    a: &Vector2<T>,
+    b: &Vector2<T>,
+    c: &Vector2<T>,
+    d: &Vector2<T>,
+) -> Option<(T, T)> {
+    let (r, q) = (b - a, d - c);
+    let denominator = r.perp(&q);
+    if denominator == T::zero() {
+        return None;
+    }
+    let s = (c - a).perp(&q) / denominator;
+    let t = (c - a).perp(&r) / denominator;
+    let within = |x: T| x >= T::zero() && x <= T::cast_from(1);
+    (within(s) && within(t)).then_some((s, t))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::splines::Spline;
+    use alloc::vec;
+
+    #[test]
+    fn it_offsets_circles() {
+        let circle = BSpline::circle();
+        let (outside, error) = circle.nurbs().offset(0.25, 1e-4).unwrap();
+        assert!(error <= 1e-4);
+        let (inside, error) = circle.nurbs().offset(-0.25, 1e-4).unwrap();
+        assert!(error <= 1e-4);
+
+        // the circle runs clockwise so its left is outwards
+        for (curve, radius) in [(&outside, 0.75), (&inside, 0.25)] {
+            let (start, end) = curve.range().into_inner();
+            assert!((curve.at(start) - curve.at(end)).norm() < 1e-9);
+            for i in 0..=200 {
+                let u = start + (end - start) * i as f64 / 200.;
+                assert!((curve.at(u).norm() - radius).abs() < 1e-4);
+            }
+        }
+    }
+
+    #[test]
+    fn it_removes_loops() {
+        // a tight bump whose radius of curvature is below the offset distance
+        let mut curve = BSpline::new(ControlVec::new(
+            2,
+            vec![
+                Vector2::new(-2f64, 0.),
+                Vector2::new(-0.1, 0.),
+                Vector2::new(0., 0.5),
+                Vector2::new(0.1, 0.),
+                Vector2::new(2., 0.),
+            ],
+        ));
+        curve.knots_mut().clamp_ends();
+        let distance = -0.3;
+        let (offset, error) = curve.offset(distance, 1e-3).unwrap();
+        assert!(error <= 1e-3);
+
+        // without the loop the offset never crosses itself
+        let points = offset.control_points();
+        for i in 0..points.len() - 1 {
+            for j in i + 2..points.len() - 1 {
+                assert!(crossing(&points[i], &points[i + 1], &points[j], &points[j + 1]).is_none());
+            }
+        }
+
+        // every remaining point is at least the distance away from the curve
+        let samples: Vec<_> = (0..=2000)
+            .map(|i| curve.at(2. + 3. * i as f64 / 2000.))
+            .collect();
+        for p in points {
+            let nearest = samples
+                .iter()
+                .map(|s| (s - p).norm())
+                .fold(f64::MAX, f64::min);
+            assert!(nearest > -distance - 1e-2);
+        }
+    }
+
+    #[test]
+    fn it_keeps_distant_crossings() {
+        // a curve crossing itself around a loop much longer than the offset distance
+        let mut curve = BSpline::new(ControlVec::new(
+            3,
+            vec![
+                Vector2::new(-1f64, 0.),
+                Vector2::new(2., 2.),
+                Vector2::new(-2., 2.),
+                Vector2::new(1., 0.),
+            ],
+        ));
+        curve.knots_mut().clamp_ends();
+        let (offset, _) = curve.offset(0.05, 1e-4).unwrap();
+
+        let points = offset.control_points();
+        let crosses = (0..points.len() - 1).any(|i| {
+            (i + 2..points.len() - 1)
+                .any(|j| crossing(&points[i], &points[i + 1], &points[j], &points[j + 1]).is_some())
+        });
+        assert!(crosses);
+    }
+
+    #[test]
+    fn it_rejects_curves_without_direction() {
+        let point = Vector2::new(1f64, 2.);
+        let mut curve = BSpline::new(ControlVec::new(1, vec![point, point]));
+        curve.knots_mut().clamp_ends();
+        assert!(curve.offset(0.5, 1e-3).is_none());
+    }
+}
@@ -0,0 +1,334 @@
+use crate::algorithms::{basis_second_derivatives, uniform_knots};
+use crate::knots::Knots;
+use crate::splines::approximate::least_squares;
+use crate::splines::{BSpline, Parametrization};
+use crate::types::{Real, Vector};
+use alloc::vec::Vec;
+use nalgebra::allocator::Allocator;
+use nalgebra::{DMatrix, DefaultAllocator, Dim};
+
+/// How the roughness of a smoothing spline is measured.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Penalty {
+    /// Sum of the squared differences of the given order between neighbouring control
+    /// points, as in P-splines.
+    Difference(usize),
+    /// Integral of the squared second derivative over the range of the spline, its
+    /// bending energy.
+    Bending,
+}
+
+/// Builder of a smoothing spline, a least squares fit of a spline with evenly spaced
+/// knots to points which trades closeness to the points against the roughness of the
+/// spline, weighed by `lambda`. Functions of one variable are fitted by giving the
+/// values as 1D points and their arguments as the parameters.
+#[derive(Debug, Clone)]
+pub struct Smoothing<'a, D: Dim, T: Real>
+where
+    DefaultAllocator: Allocator<T, D>,
+{
+    points: &'a [Vector<D, T>],
+    count: usize,
+    degree: usize,
+    parametrization: Parametrization,
+    parameters: Option<&'a [T]>,
+    weights: Option<&'a [T]>,
+    penalty: Penalty,
+    lambda: T,
+}
+
+impl<'a, D: Dim, T: Real> Smoothing<'a, D, T>
+where
+    DefaultAllocator: Allocator<T, D>,
+{
+    /// Smooth `points` by a cubic spline with `count` control points and chord length
+    /// parametrization, penalizing its bending energy with a `lambda` of zero.
+    pub fn new(points: &'a [Vector<D, T>], count: usize) -> Self {
+        Self {
+            points,
+            count,
+            degree: 3,
+            parametrization: Parametrization::ChordLength,
+            parameters: None,
+            weights: None,
+            penalty: Penalty::Bending,
+            lambda: T::zero(),
+        }
+    }
+
+    /// Set the degree of the spline.
+    pub fn degree(mut self, degree: usize) -> Self {
+        self.degree = degree;
+        self
+    }
+
+    /// Set how the points are spread over the range of the spline.
+    pub fn parametrization(mut self, parametrization: Parametrization) -> Self {
+        self.parametrization = parametrization;
+        self
+    }
+
+    /// Use the given increasing parameters, one per point, instead of a parametrization.
+    /// The spline ranges from the first parameter to the last.
+    pub fn parameters(mut self, parameters: &'a [T]) -> Self {
+        assert_eq!(
+            self.points.len(),
+            parameters.len(),
+            "one parameter per point"
+        );
+        assert!(
+            parameters.windows(2).all(|p| p[0] <= p[1]),
+            "parameters must be increasing"
+        );
+        self.parameters = Some(parameters);
+        self
+    }
+
+    /// Weigh how much each point counts towards the fit, one weight per point.
+    pub fn weights(mut self, weights: &'a [T]) -> Self {
+        assert_eq!(self.points.len(), weights.len(), "one weight per point");
+        self.weights = Some(weights);
+        self
+    }
+
+    /// Set how the roughness of the spline is measured.
+    pub fn penalty(mut self, penalty: Penalty) -> Self {
+        self.penalty = penalty;
+        self
+    }
+
+    /// Set the weight of the roughness against the squared distances to the points.
+    /// Zero gives a plain least squares fit, larger values give smoother splines.
+    pub fn lambda(mut self, lambda: T) -> Self {
+        self.lambda = lambda;
+        self
+    }
+
+    /// Penalized least squares fit of the spline, returned with the largest distance from
+    /// a point to the spline at its parameter, or `None` if the fit cannot be solved.
+    pub fn build(self) -> Option<(BSpline<D, T>, T)> {
+        let (points, count, degree) = (self.points, self.count, self.degree);
+        assert!(degree > 0, "cannot smooth with degree 0");
+        assert!(count > degree, "not enough control points for the degree");
+        let parameters = match self.parameters {
+            Some(parameters) => parameters.to_vec(),
+            None => self.parametrization.parameters(points, false),
+        };
+        let (start, end) = (parameters[0], parameters[parameters.len() - 1]);
+        assert!(start < end, "parameters must span a range");
+        let knots = uniform_knots(degree, count, start, end);
+
+        let penalty = match self.penalty {
+            Penalty::Difference(order) => {
+                assert!(order < count, "not enough control points for the order");
+                difference_penalty(count, order)
+            }
+            Penalty::Bending => bending_penalty(&knots, degree),
+        } * self.lambda;
+        least_squares(
+            points,
+            &parameters,
+            self.weights,
+            Some(&penalty),
+            false,
+            degree,
+            knots,
+        )
+    }
+}
+
+/// Sum of the squared differences of `order` between `count` control points, as a
+/// quadratic form on the control points.
+fn difference_penalty<T: Real>(count: usize, order: usize) -> DMatrix<T> {
+    // signed binomial coefficients of the difference
+    let mut coefficients = alloc::vec![T::cast_from(1)];
+    for _ in 0..order {
+        let mut next = alloc::vec![T::zero(); coefficients.len() + 1];
+        for (i, &c) in coefficients.iter().enumerate() {
+            next[i] -= c;
+            next[i + 1] += c;
+        }
+        coefficients = next;
+    }
+
+    let mut penalty = DMatrix::zeros(count, count);
+    for first in 0..count - order {
+        for (i, &a) in coefficients.iter().enumerate() {
+            for (j, &b) in coefficients.iter().enumerate() {
+                penalty[(first + i, first + j)] += a * b;
+            }
+        }
+    }
+    penalty
+}
+
+/// Integral of the squared second derivative of a clamped spline with `knots`, as a
+/// quadratic form on the control points. Gauss Legendre quadrature over each span is
+/// exact for the piecewise polynomial integrand.
+fn bending_penalty<T: Real>(knots: &[T], degree: usize) -> DMatrix<T> {
+    let count = knots.len() - degree - 1;
+    let k = Knots::new(degree, knots);
+    let rule = gauss_legendre(degree.max(2) - 1);
+    let half = T::cast_from(1) / T::cast_from(2);
+
+    let mut penalty = DMatrix::zeros(count, count);
+    for span in degree..count {
+        let (start, end) = (knots[span], knots[span + 1]);
+        if start == end {
+            continue;
+        }
+        let (middle, radius) = ((start + end) * half, (end - start) * half);
+        for &(x, weight) in &rule {
+            let values = basis_second_derivatives(middle + radius * x, span, degree, &k);
+            for (i, &a) in values.iter().enumerate() {
+                for (j, &b) in values.iter().enumerate() {
+                    penalty[(span - degree + i, span - degree + j)] += a * b * weight * radius;
+                }
+            }
+        }
+    }
+    penalty
+}
+
+/// Nodes and weights of the Gauss Legendre quadrature of `count` points over minus one
+/// to one, found by Newton's method on the Legendre polynomial.
+fn gauss_legendre<T: Real>(count: usize) -> Vec<(T, T)> {
+    let n = T::cast_from(count);
+    let one = T::cast_from(1);
+    let (quarter, half) = (one / T::cast_from(4), one / T::cast_from(2));
+    (0..count)
+        .map(|i| {
+            let mut x = (T::pi() * (T::cast_from(i + 1) - quarter) / (n + half)).cos();
+            let mut derivative = one;
+            for _ in 0..100 {
+                // Legendre polynomial of degree count at x by its recurrence
+                let (mut p, mut previous) = (x, one);
+                for k in 2..=count {
+                    let k = T::cast_from(k);
+                    let next = ((k + k - one) * x * p - (k - one) * previous) / k;
+                    previous = p;
+                    p = next;
+                }
+                derivative = n * (x * p - previous) / (x * x - one);
+                let step = p / derivative;
+                x -= step;
+                if step.abs() <= T::default_epsilon() {
+                    break;
+                }
+            }
+            (
+                x,
+                T::cast_from(2) / ((one - x * x) * derivative * derivative),
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::splines::Spline;
+    use nalgebra::{Vector1, Vector2};
+
+    fn noisy() -> (Vec<f64>, Vec<Vector1<f64>>) {
+        (0..100)
+            .map(|i| {
+                let x = i as f64 / 10.;
+                let noise = ((i * 37 % 11) as f64 - 5.) / 100.;
+                (x, Vector1::new(x.sin() + noise))
+            })
+            .unzip()
+    }
+
+    #[test]
+    fn it_integrates_exactly() {
+        for count in 1..6 {
+            let rule = gauss_legendre::<f64>(count);
+            // polynomials up to degree 2 * count - 1 are integrated exactly
+            for power in 0..2 * count {
+                let integral: f64 = rule.iter().map(|&(x, w)| w * x.powi(power as i32)).sum();
+                let exact = if power % 2 == 0 {
+                    2. / (power + 1) as f64
+                } else {
+                    0.
+                };
+                assert!((integral - exact).abs() < 1e-12);
+            }
+        }
+
+        // the control points of x squared are products of consecutive knots, and its
+        // second derivative is two everywhere
+        let knots = uniform_knots(2, 5, 0., 3.);
+        let control = DMatrix::from_fn(5, 1, |i, _| knots[i + 1] * knots[i + 2]);
+        let energy = control.transpose() * bending_penalty::<f64>(&knots, 2) * control;
+        assert!((energy[(0, 0)] - 12.).abs() < 1e-9);
+    }
+
+    #[test]
+    fn it_smooths_functions() {
+        let (xs, ys) = noisy();
+        let (smooth, error) = Smoothing::new(&ys, 30)
+            .parameters(&xs)
+            .lambda(0.1)
+            .build()
+            .unwrap();
+        assert_eq!(0.0..=9.9, smooth.range());
+        assert!(error < 0.1);
+        for &x in &xs {
+            assert!((smooth.at(x).x - x.sin()).abs() < 0.05);
+        }
+
+        // without bending the spline is the straight line of linear regression
+        let (line, _) = Smoothing::new(&ys, 30)
+            .parameters(&xs)
+            .lambda(1e9)
+            .build()
+            .unwrap();
+        let n = xs.len() as f64;
+        let (mean_x, mean_y) = (
+            xs.iter().sum::<f64>() / n,
+            ys.iter().map(|y| y.x).sum::<f64>() / n,
+        );
+        let slope = xs
+            .iter()
+            .zip(&ys)
+            .map(|(x, y)| (x - mean_x) * (y.x - mean_y))
+            .sum::<f64>()
+            / xs.iter().map(|x| (x - mean_x).powi(2)).sum::<f64>();
+        for &x in &xs {
+            let expected = mean_y + slope * (x - mean_x);
+            assert!((line.at(x).x - expected).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn it_penalizes_differences() {
+        let points: Vec<_> = (0..60)
+            .map(|i| {
+                let t = i as f64 / 10.;
+                Vector2::new(t.cos() * (1. + t / 5.), t.sin())
+            })
+            .collect();
+        let mut previous = 0.;
+        for lambda in [0., 0.1, 1., 10.] {
+            let (_, error) = Smoothing::new(&points, 15)
+                .penalty(Penalty::Difference(2))
+                .lambda(lambda)
+                .build()
+                .unwrap();
+            assert!(error >= previous);
+            previous = error;
+        }
+
+        // first differences pull every control point to the mean of the points
+        let (flat, _) = Smoothing::new(&points, 15)
+            .penalty(Penalty::Difference(1))
+            .lambda(1e9)
+            .build()
+            .unwrap();
+        let mean = points.iter().sum::<Vector2<f64>>() / points.len() as f64;
+        for p in flat.control_points().iter() {
+            assert!((p - mean).norm() < 1e-4);
+        }
+    }
+}
@@ -1,13 +1,21 @@
+mod approximate;
 mod b_surface;
+mod interpolate;
 mod nurbs;
+mod offset;
+mod primitives;
+mod trimmed;
 
 use crate::types::{Scalar, Vector};
 use core::ops::RangeInclusive;
 use nalgebra::{DefaultAllocator, Dim};
 
+pub use crate::rational::RationalSurface;
 use crate::step_iter::StepIter;
+pub use approximate::SurfaceApproximation;
 pub use b_surface::BSurface;
 pub use nurbs::NURBSurface;
+pub use trimmed::{TrimCurve, TrimmedSurface};
 
 /// 2D surface coordinate.
 pub type UV<T> = (T, T);
@@ -51,6 +59,15 @@ where
     }
 }
 
+/// 2D spline surface which can be differentiated.
+pub trait SurfaceDerivatives<D: Dim, T: Scalar + 'static>: Surface<D, T>
+where
+    DefaultAllocator: nalgebra::allocator::Allocator<T, D>,
+{
+    /// Partial derivatives in u and v at coordinate `uv`.
+    fn derivatives(&self, uv: UV<T>) -> (Vector<D, T>, Vector<D, T>);
+}
+
 #[derive(Debug, Clone)]
 struct UVRange<T> {
     u: StepIter<T>,
@@ -93,8 +110,21 @@ impl<T: Scalar> Iterator for UVRange<T> {
 impl<T: Scalar> ExactSizeIterator for UVRange<T> {}
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
+    use alloc::vec::Vec;
+    use nalgebra::Vector4;
+
+    /// Rows of four control points at uneven heights with varying weights, for the
+    /// surfaces shared by the tests.
+    pub(crate) fn bumpy_points(rows: usize) -> Vec<Vector4<f64>> {
+        (0..rows * 4)
+            .map(|i| {
+                let w = 1. + (i % 3) as f64 / 2.;
+                Vector4::new((i % 4) as f64, (i / 4) as f64, (i * 7 % 5) as f64, w)
+            })
+            .collect()
+    }
 
     #[test]
     fn it_has_uv_len() {
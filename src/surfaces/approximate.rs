@@ -0,0 +1,349 @@
+use crate::algorithms::{basis_functions, solve, uniform_knots};
+use crate::control_points::ControlGrid;
+use crate::knots::Knots;
+use crate::rational::Patches;
+use crate::surfaces::{BSurface, Surface, UV};
+use crate::types::Real;
+use alloc::vec::Vec;
+use nalgebra::{Const, DMatrix, Matrix3, Vector3};
+
+impl<T: Real> BSurface<Const<3>, T> {
+    /// Surface of `degree` in both directions with a grid of `u_count` by `v_count` control
+    /// points over the ranges zero to one, which passes as closely as possible to scattered
+    /// `points` in the least squares sense. The points are parametrized by projecting them
+    /// onto their plane of best fit.
+    /// Returned with the largest distance from a point to the surface at its parameters,
+    /// or `None` if the fit cannot be solved.
+    pub fn approximate(
+        points: &[Vector3<T>],
+        degree: usize,
+        (u_count, v_count): UV<usize>,
+    ) -> Option<(Self, T)> {
+        SurfaceApproximation::new(points, u_count, v_count)
+            .degree(degree)
+            .build()
+    }
+}
+
+/// Builder of a least squares fit of a surface with a fixed grid of control points to
+/// scattered points. The points either come with their parameters or are projected onto
+/// their plane of best fit, a smoothing term keeps the control points of sparsely sampled
+/// regions in line with their neighbours, and the parameters may be corrected by finding
+/// the closest point on the fitted surface before fitting again.
+#[derive(Debug, Clone)]
+pub struct SurfaceApproximation<'a, T: Real> {
+    points: &'a [Vector3<T>],
+    parameters: Option<&'a [UV<T>]>,
+    count: UV<usize>,
+    degree: usize,
+    smoothing: T,
+    corrections: usize,
+}
+
+impl<'a, T: Real> SurfaceApproximation<'a, T> {
+    /// Approximate `points` by a bicubic surface with `u_count` by `v_count` control points,
+    /// without smoothing or parameter correction.
+    pub fn new(points: &'a [Vector3<T>], u_count: usize, v_count: usize) -> Self {
+        Self {
+            points,
+            parameters: None,
+            count: (u_count, v_count),
+            degree: 3,
+            smoothing: T::zero(),
+            corrections: 0,
+        }
+    }
+
+    /// Set the degree of the surface in both directions.
+    pub fn degree(mut self, degree: usize) -> Self {
+        self.degree = degree;
+        self
+    }
+
+    /// Use the given parameters of the points within zero and one, one per point,
+    /// instead of projecting the points.
+    pub fn parameters(mut self, parameters: &'a [UV<T>]) -> Self {
+        assert_eq!(
+            self.points.len(),
+            parameters.len(),
+            "one parameter per point"
+        );
+        self.parameters = Some(parameters);
+        self
+    }
+
+    /// Set the weight of the second differences of the control grid against the distances
+    /// to the points, relative to the overall sampling density. Zero fits the points only.
+    pub fn smoothing(mut self, smoothing: T) -> Self {
+        self.smoothing = smoothing;
+        self
+    }
+
+    /// Set how many times the parameters are moved to the closest points on the surface
+    /// and the surface is fitted again.
+    pub fn corrections(mut self, corrections: usize) -> Self {
+        self.corrections = corrections;
+        self
+    }
+
+    /// Least squares fit of the surface, returned with the largest distance from a point
+    /// to the surface at its final parameters, or `None` if the fit cannot be solved or the
+    /// points to project do not span a plane.
+    pub fn build(self) -> Option<(BSurface<Const<3>, T>, T)> {
+        let (points, degree) = (self.points, self.degree);
+        let (u_count, v_count) = self.count;
+        assert!(degree > 0, "cannot approximate with degree 0");
+        assert!(
+            u_count > degree && v_count > degree,
+            "not enough control points for the degree"
+        );
+        let mut parameters = match self.parameters {
+            Some(parameters) => {
+                let range = T::zero()..=T::cast_from(1);
+                assert!(
+                    parameters
+                        .iter()
+                        .all(|(u, v)| range.contains(u) && range.contains(v)),
+                    "parameters must be within zero and one"
+                );
+                parameters.to_vec()
+            }
+            None => projected(points)?,
+        };
+
+        let u_knots = uniform_knots(degree, u_count, T::zero(), T::cast_from(1));
+        let v_knots = uniform_knots(degree, v_count, T::zero(), T::cast_from(1));
+        let mut surface = self.fit(&parameters, &u_knots, &v_knots)?;
+        for _ in 0..self.corrections {
+            let patches = Patches::new(&surface);
+            for (uv, point) in parameters.iter_mut().zip(points) {
+                *uv = patches.closest(patches.patch(*uv), *point);
+            }
+            surface = self.fit(&parameters, &u_knots, &v_knots)?;
+        }
+
+        let error = points
+            .iter()
+            .zip(&parameters)
+            .map(|(point, &uv)| (surface.at(uv) - point).norm())
+            .fold(T::zero(), |a, b| a.max(b));
+        Some((surface, error))
+    }
+
+    /// Solve the smoothed normal equations for the control grid at `parameters`.
+    fn fit(
+        &self,
+        parameters: &[UV<T>],
+        u_knots: &[T],
+        v_knots: &[T],
+    ) -> Option<BSurface<Const<3>, T>> {
+        let degree = self.degree;
+        let (u_count, v_count) = self.count;
+        let unknowns = u_count * v_count;
+        let (uk, vk) = (Knots::new(degree, u_knots), Knots::new(degree, v_knots));
+
+        let mut normal = DMatrix::zeros(unknowns, unknowns);
+        let mut values = alloc::vec![Vector3::zeros(); unknowns];
+        let mut columns = Vec::with_capacity((degree + 1) * (degree + 1));
+        for (point, &(u, v)) in self.points.iter().zip(parameters) {
+            let (u_span, v_span) = (uk.find_span(u), vk.find_span(v));
+            let u_basis = basis_functions(u, u_span, degree, &uk);
+            let v_basis = basis_functions(v, v_span, degree, &vk);
+            columns.clear();
+            for (j, &b) in v_basis.iter().enumerate() {
+                for (i, &a) in u_basis.iter().enumerate() {
+                    let index = (v_span - degree + j) * u_count + u_span - degree + i;
+                    columns.push((index, a * b));
+                }
+            }
+            for &(row, a) in &columns {
+                values[row] += point * a;
+                for &(column, b) in &columns {
+                    normal[(row, column)] += a * b;
+                }
+            }
+        }
+
+        if self.smoothing > T::zero() {
+            let penalty = penalty(u_count, v_count);
+            let trace = penalty.trace();
+            if trace > T::zero() {
+                // scaled so that the smoothing does not depend on the number of points
+                normal += penalty * (self.smoothing * normal.trace() / trace);
+            }
+        }
+
+        let control = solve(normal, &values)?;
+        Some(BSurface::with_knots(
+            ControlGrid::new(degree, u_count, control),
+            u_knots.to_vec(),
+            v_knots.to_vec(),
+        ))
+    }
+}
+
+/// Parameters of points projected onto their plane of best fit, scaled to fill the ranges
+/// zero to one. `None` if they lie on a line.
+fn projected<T: Real>(points: &[Vector3<T>]) -> Option<Vec<UV<T>>> {
+    let count = T::cast_from(points.len());
+    let centroid = points
+        .iter()
+        .fold(Vector3::zeros(), |sum: Vector3<T>, p| sum + p)
+        / count;
+    let covariance = points
+        .iter()
+        .map(|p| (p - centroid) * (p - centroid).transpose())
+        .fold(Matrix3::zeros(), |sum: Matrix3<T>, m| sum + m);
+    let eigen = covariance.symmetric_eigen();
+    let mut values = eigen.eigenvalues.as_slice().to_vec();
+    values.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    if values[1] <= values[2] * T::default_epsilon() {
+        return None;
+    }
+
+    // the u axis follows the coordinate axis closest to the plane, so that the points of
+    // an axis aligned scan fill the parameter ranges
+    let normal = eigen
+        .eigenvectors
+        .column(eigen.eigenvalues.imin())
+        .into_owned();
+    let axis = Vector3::ith(normal.iamin(), T::cast_from(1));
+    let u_axis = (axis - normal * normal.dot(&axis)).normalize();
+    let axes = (u_axis, normal.cross(&u_axis));
+
+    let projected: Vec<UV<T>> = points
+        .iter()
+        .map(|p| ((p - centroid).dot(&axes.0), (p - centroid).dot(&axes.1)))
+        .collect();
+    let bounds = |x: fn(&UV<T>) -> T| {
+        projected.iter().map(x).fold(
+            (T::max_value().unwrap(), T::min_value().unwrap()),
+            |(low, high), x| (low.min(x), high.max(x)),
+        )
+    };
+    let (u_bounds, v_bounds) = (bounds(|uv| uv.0), bounds(|uv| uv.1));
+    let scale =
+        |x: T, (low, high): (T, T)| ((x - low) / (high - low)).clamp(T::zero(), T::cast_from(1));
+    Some(
+        projected
+            .into_iter()
+            .map(|(u, v)| (scale(u, u_bounds), scale(v, v_bounds)))
+            .collect(),
+    )
+}
+
+/// Sum of the squared second differences along every row and column of a control grid
+/// of `u_count` by `v_count` points, as a quadratic form on the control points.
+fn penalty<T: Real>(u_count: usize, v_count: usize) -> DMatrix<T> {
+    let unknowns = u_count * v_count;
+    let mut penalty = DMatrix::zeros(unknowns, unknowns);
+    let mut add = |indices: [usize; 3]| {
+        let weights = [T::cast_from(1), -T::cast_from(2), T::cast_from(1)];
+        for (&row, &a) in indices.iter().zip(&weights) {
+            for (&column, &b) in indices.iter().zip(&weights) {
+                penalty[(row, column)] += a * b;
+            }
+        }
+    };
+    for j in 0..v_count {
+        for i in 1..u_count.saturating_sub(1) {
+            let index = j * u_count + i;
+            add([index - 1, index, index + 1]);
+        }
+    }
+    for j in 1..v_count.saturating_sub(1) {
+        for i in 0..u_count {
+            let index = j * u_count + i;
+            add([index - u_count, index, index + u_count]);
+        }
+    }
+    penalty
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn height(x: f64, y: f64) -> f64 {
+        (x * 1.3).sin() * (y * 0.7).cos()
+    }
+
+    /// Pseudo random points over the square from zero to four, leaving out a hole.
+    fn scattered(hole: bool) -> Vec<Vector3<f64>> {
+        (0..600)
+            .map(|i| {
+                let x = (i * 97 % 600) as f64 / 150.;
+                let y = (i * 389 % 601) as f64 / 150.;
+                Vector3::new(x, y, height(x, y))
+            })
+            .filter(|p| !hole || (p.x - 1.).hypot(p.y - 1.) > 0.8)
+            .collect()
+    }
+
+    #[test]
+    fn it_reproduces_surfaces_from_parameters() {
+        let control = (0..25)
+            .map(|i| Vector3::new((i % 5) as f64, (i / 5) as f64, ((i * 7) % 5) as f64 / 4.))
+            .collect();
+        let surface = BSurface::with_knots(
+            ControlGrid::new(3, 5, control),
+            uniform_knots(3, 5, 0., 1.),
+            uniform_knots(3, 5, 0., 1.),
+        );
+        let parameters: Vec<_> = (0..200)
+            .map(|i| ((i * 13 % 200) as f64 / 199., (i * 71 % 199) as f64 / 198.))
+            .collect();
+        let points: Vec<_> = parameters.iter().map(|&uv| surface.at(uv)).collect();
+
+        let (fitted, error) = SurfaceApproximation::new(&points, 5, 5)
+            .parameters(&parameters)
+            .build()
+            .unwrap();
+        assert!(error < 1e-9);
+        for (a, b) in fitted.control_points().iter().zip(surface.control_points()) {
+            assert!((a - b).norm() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn it_approximates_scattered_points() {
+        let points = scattered(false);
+        let (surface, error) = BSurface::approximate(&points, 3, (8, 8)).unwrap();
+        assert_eq!(0.0..=1.0, surface.u_range());
+        assert_eq!(64, surface.control_points().len());
+        assert!(error < 0.05);
+
+        // moving the parameters to the closest points brings the surface closer
+        let (_, corrected) = SurfaceApproximation::new(&points, 8, 8)
+            .corrections(3)
+            .build()
+            .unwrap();
+        assert!(corrected < error);
+    }
+
+    #[test]
+    fn it_smooths_sparse_regions() {
+        let points = scattered(true);
+        let fit = |smoothing| {
+            SurfaceApproximation::new(&points, 12, 12)
+                .smoothing(smoothing)
+                .build()
+        };
+        let (smooth, error) = fit(1e-3).unwrap();
+        assert!(error < 0.1);
+
+        // the hole is bridged close to the sampled surface
+        let worst = |surface: &BSurface<Const<3>, f64>| {
+            (0..=20)
+                .flat_map(|j| (0..=20).map(move |i| (i as f64 / 20., j as f64 / 20.)))
+                .map(|uv| {
+                    let p = surface.at(uv);
+                    (p.z - height(p.x, p.y)).abs()
+                })
+                .fold(0., f64::max)
+        };
+        assert!(worst(&smooth) < 0.2);
+        // without smoothing the control points over the hole are left undetermined
+        assert!(fit(0.).is_none());
+    }
+}
@@ -1,7 +1,10 @@
-use crate::algorithms::cox_de_boor_uv;
-use crate::control_points::ControlGrid;
+use crate::algorithms::{basis_derivatives, basis_functions, cox_de_boor_u, cox_de_boor_uv, trim};
+use crate::bezier::{decompose_surface, BezierPatch, Linear};
+use crate::control_points::{ControlGrid, ControlVec};
+use crate::grid::Grid;
 use crate::knots::{Knots, KnotsMut};
-use crate::surfaces::{NURBSurface, Surface, UV};
+use crate::splines::BSpline;
+use crate::surfaces::{NURBSurface, Surface, SurfaceDerivatives, UV};
 use crate::types::{Scalar, Vector};
 use alloc::vec::Vec;
 use core::ops::RangeInclusive;
@@ -16,8 +19,8 @@ where
     DefaultAllocator: Allocator<T, D>,
 {
     control_points: ControlGrid<Vector<D, T>>,
-    u_knots: Vec<usize>,
-    v_knots: Vec<usize>,
+    u_knots: Vec<T>,
+    v_knots: Vec<T>,
 }
 
 impl<D: Dim, T: Scalar> BSurface<D, T>
@@ -33,26 +36,51 @@ where
         }
     }
 
+    /// Create a new BSurface with explicit knots, which must number the control points
+    /// in each direction including wrapping plus the degree plus one.
+    pub fn with_knots(
+        control_points: ControlGrid<Vector<D, T>>,
+        u_knots: Vec<T>,
+        v_knots: Vec<T>,
+    ) -> Self {
+        let degree = control_points.degree();
+        assert_eq!(
+            control_points.u_len() + degree + 1,
+            u_knots.len(),
+            "wrong number of u knots"
+        );
+        assert_eq!(
+            control_points.v_len() + degree + 1,
+            v_knots.len(),
+            "wrong number of v knots"
+        );
+        Self {
+            control_points,
+            u_knots,
+            v_knots,
+        }
+    }
+
     /// Degree of the surface.
     pub fn degree(&self) -> usize {
         self.control_points.degree()
     }
 
     /// u knots.
-    pub fn u_knots(&self) -> Knots {
+    pub fn u_knots(&self) -> Knots<'_, T> {
         Knots::new(self.degree(), &self.u_knots)
     }
     /// Mutable u knots.
-    pub fn u_knots_mut(&mut self) -> KnotsMut {
+    pub fn u_knots_mut(&mut self) -> KnotsMut<'_, T> {
         KnotsMut::new(self.degree(), &mut self.u_knots)
     }
 
     /// v knots.
-    pub fn v_knots(&self) -> Knots {
+    pub fn v_knots(&self) -> Knots<'_, T> {
         Knots::new(self.degree(), &self.v_knots)
     }
     /// Mutable v knots.
-    pub fn v_knots_mut(&mut self) -> KnotsMut {
+    pub fn v_knots_mut(&mut self) -> KnotsMut<'_, T> {
         KnotsMut::new(self.degree(), &mut self.v_knots)
     }
 
@@ -73,9 +101,205 @@ where
 
     /// Convert an N degree BSpline into a N-1 degree NURBS.
     /// The final degree becomes the weight value.
-    pub fn nurbs(&self) -> NURBSurface<D, T> {
+    pub fn nurbs(&self) -> NURBSurface<'_, D, T> {
         NURBSurface::new(self)
     }
+
+    /// Swap the u and v directions, transposing the control grid and swapping the knots.
+    pub fn swap_uv(&mut self) {
+        self.control_points = self.control_points.transposed();
+        core::mem::swap(&mut self.u_knots, &mut self.v_knots);
+    }
+
+    /// Reverse the direction of u over the same range.
+    pub fn reverse_u(&mut self) {
+        self.control_points = self.control_points.reversed_u();
+        self.u_knots = self.u_knots().reversed();
+    }
+
+    /// Reverse the direction of v over the same range.
+    pub fn reverse_v(&mut self) {
+        self.control_points = self.control_points.reversed_v();
+        self.v_knots = self.v_knots().reversed();
+    }
+
+    /// Curve along v at a fixed `u`, sharing the v knots and degree of the surface.
+    pub fn iso_u(&self, u: T) -> BSpline<D, T> {
+        self.iso_u_mapped(u, Clone::clone, Clone::clone)
+    }
+
+    /// Curve along u at a fixed `v`, sharing the u knots and degree of the surface.
+    pub fn iso_v(&self, v: T) -> BSpline<D, T> {
+        self.iso_v_mapped(v, Clone::clone, Clone::clone)
+    }
+
+    /// Iso curve at `u` with the control points mapped by `f` while collapsing and by `g` after.
+    pub(crate) fn iso_u_mapped(
+        &self,
+        u: T,
+        f: impl Fn(&Vector<D, T>) -> Vector<D, T>,
+        g: impl Fn(&Vector<D, T>) -> Vector<D, T>,
+    ) -> BSpline<D, T> {
+        let grid = &self.control_points;
+        let wrapping = grid.v_wrapping();
+        let len = grid.v_len() - if wrapping { self.degree() } else { 0 };
+        let points = (0..len)
+            .map(|v| {
+                g(&cox_de_boor_u(u, self.degree(), &self.u_knots(), |i| {
+                    f(&grid[(i, v)])
+                }))
+            })
+            .collect();
+
+        let mut control_points = ControlVec::new(self.degree(), points);
+        control_points.set_wrapping(wrapping);
+        BSpline::with_knots(control_points, self.v_knots.clone())
+    }
+
+    /// Iso curve at `v` with the control points mapped by `f` while collapsing and by `g` after.
+    pub(crate) fn iso_v_mapped(
+        &self,
+        v: T,
+        f: impl Fn(&Vector<D, T>) -> Vector<D, T>,
+        g: impl Fn(&Vector<D, T>) -> Vector<D, T>,
+    ) -> BSpline<D, T> {
+        let grid = &self.control_points;
+        let wrapping = grid.u_wrapping();
+        let len = grid.u_len() - if wrapping { self.degree() } else { 0 };
+        let points = (0..len)
+            .map(|u| {
+                g(&cox_de_boor_u(v, self.degree(), &self.v_knots(), |j| {
+                    f(&grid[(u, j)])
+                }))
+            })
+            .collect();
+
+        let mut control_points = ControlVec::new(self.degree(), points);
+        control_points.set_wrapping(wrapping);
+        BSpline::with_knots(control_points, self.u_knots.clone())
+    }
+
+    /// Standalone clamped surface covering `u_range` and `v_range`, which lie within the ranges of the surface.
+    pub fn subsurface(&self, u_range: RangeInclusive<T>, v_range: RangeInclusive<T>) -> Self {
+        self.subsurface_mapped(u_range, v_range, Clone::clone, Clone::clone)
+    }
+
+    /// Subsurface with the control points mapped by `f` while inserting knots and by `g` after.
+    pub(crate) fn subsurface_mapped(
+        &self,
+        u_range: RangeInclusive<T>,
+        v_range: RangeInclusive<T>,
+        f: impl Fn(&Vector<D, T>) -> Vector<D, T>,
+        g: impl Fn(&Vector<D, T>) -> Vector<D, T>,
+    ) -> Self {
+        let within = |range: &RangeInclusive<T>, knots: Knots<'_, T>| {
+            let (first, last) = knots.range().into_inner();
+            first <= *range.start() && range.start() < range.end() && *range.end() <= last
+        };
+        assert!(within(&u_range, self.u_knots()), "u range out of range");
+        assert!(within(&v_range, self.v_knots()), "v range out of range");
+
+        let degree = self.degree();
+        let grid = &self.control_points;
+
+        // trim the rows in u then the resulting columns in v
+        let mut u_knots = Vec::new();
+        let rows: Vec<Vec<_>> = (0..grid.v_len())
+            .map(|v| {
+                let row = (0..grid.u_len()).map(|u| f(&grid[(u, v)])).collect();
+                let (knots, row) = trim(
+                    degree,
+                    self.u_knots.clone(),
+                    row,
+                    u_range.clone().into_inner(),
+                );
+                u_knots = knots;
+                row
+            })
+            .collect();
+
+        let mut v_knots = Vec::new();
+        let columns: Vec<Vec<_>> = (0..rows[0].len())
+            .map(|u| {
+                let column = rows.iter().map(|row| row[u].clone()).collect();
+                let (knots, column) = trim(
+                    degree,
+                    self.v_knots.clone(),
+                    column,
+                    v_range.clone().into_inner(),
+                );
+                v_knots = knots;
+                column
+            })
+            .collect();
+
+        let points = (0..columns[0].len())
+            .flat_map(|v| {
+                columns
+                    .iter()
+                    .map(|column| g(&column[v]))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        Self::with_knots(
+            ControlGrid::new(degree, columns.len(), points),
+            u_knots,
+            v_knots,
+        )
+    }
+
+    /// Point and its partial derivatives in u and v at `uv`, with the control points
+    /// mapped by `f`, from the basis functions and their derivatives at `uv`.
+    pub(crate) fn derivatives_mapped(
+        &self,
+        (u, v): UV<T>,
+        f: impl Fn(&Vector<D, T>) -> Vector<D, T>,
+    ) -> (Vector<D, T>, Vector<D, T>, Vector<D, T>) {
+        let (u_knots, v_knots) = (self.u_knots(), self.v_knots());
+        assert!(u_knots.range().contains(&u), "u out of range");
+        assert!(v_knots.range().contains(&v), "v out of range");
+
+        let degree = self.degree();
+        let (u_span, v_span) = (u_knots.find_span(u), v_knots.find_span(v));
+        let (u_basis, du_basis) = (
+            basis_functions(u, u_span, degree, &u_knots),
+            basis_derivatives(u, u_span, degree, &u_knots),
+        );
+        let (v_basis, dv_basis) = (
+            basis_functions(v, v_span, degree, &v_knots),
+            basis_derivatives(v, v_span, degree, &v_knots),
+        );
+
+        let zero = f(&self.control_points[(0, 0)]) * T::default();
+        let (mut point, mut du, mut dv) = (zero.clone(), zero.clone(), zero);
+        for j in 0..=degree {
+            for i in 0..=degree {
+                let p = f(&self.control_points[(u_span - degree + i, v_span - degree + j)]);
+                point += &p * (u_basis[i] * v_basis[j]);
+                du += &p * (du_basis[i] * v_basis[j]);
+                dv += p * (u_basis[i] * dv_basis[j]);
+            }
+        }
+        (point, du, dv)
+    }
+
+    /// Bezier patches covering the range of the surface with each control point mapped by `f`.
+    pub(crate) fn bezier_patches<P: Linear<T>>(
+        &self,
+        f: impl Fn(&Vector<D, T>) -> P,
+    ) -> Vec<BezierPatch<T, P>> {
+        let grid = &self.control_points;
+        let f = &f;
+        let points = (0..grid.v_len())
+            .flat_map(|v| (0..grid.u_len()).map(move |u| f(&grid[(u, v)])))
+            .collect();
+        decompose_surface(
+            self.degree(),
+            self.u_knots.clone(),
+            self.v_knots.clone(),
+            Grid::new(grid.u_len(), points),
+        )
+    }
 }
 
 impl<D: Dim, T: Scalar> Surface<D, T> for BSurface<D, T>
@@ -84,13 +308,11 @@ where
     <DefaultAllocator as Allocator<T, D>>::Buffer: Default,
 {
     fn u_range(&self) -> RangeInclusive<T> {
-        let r = self.u_knots().range();
-        T::cast_from(*r.start())..=T::cast_from(*r.end())
+        self.u_knots().range()
     }
 
     fn v_range(&self) -> RangeInclusive<T> {
-        let r = self.v_knots().range();
-        T::cast_from(*r.start())..=T::cast_from(*r.end())
+        self.v_knots().range()
     }
 
     fn at(&self, uv: UV<T>) -> Vector<D, T> {
@@ -100,9 +322,21 @@ where
     }
 }
 
+impl<D: Dim, T: Scalar> SurfaceDerivatives<D, T> for BSurface<D, T>
+where
+    DefaultAllocator: Allocator<T, D>,
+    <DefaultAllocator as Allocator<T, D>>::Buffer: Default,
+{
+    fn derivatives(&self, uv: UV<T>) -> (Vector<D, T>, Vector<D, T>) {
+        let (_, du, dv) = self.derivatives_mapped(uv, Clone::clone);
+        (du, dv)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::surfaces::tests::bumpy_points;
     use alloc::vec;
     use nalgebra::Vector1;
 
@@ -124,4 +358,88 @@ mod tests {
         assert_eq!(2., surface.at((1., 2.)).x);
         assert_eq!(3., surface.at((2., 2.)).x);
     }
+
+    /// Quadratic surface wrapping in u through `points` in rows of four.
+    fn wrapped<D: Dim>(points: Vec<Vector<D, f64>>) -> BSurface<D, f64>
+    where
+        DefaultAllocator: Allocator<f64, D>,
+    {
+        let mut grid = ControlGrid::new(2, 4, points);
+        grid.set_u_wrapping(true);
+        BSurface::new(grid)
+    }
+
+    #[test]
+    fn it_extracts_iso_curves() {
+        use crate::splines::Spline;
+
+        let surface = wrapped(bumpy_points(4));
+        let nurbs = surface.nurbs();
+
+        for (u, v) in surface.quantize_range(0.25) {
+            assert!((surface.iso_u(u).at(v) - surface.at((u, v))).norm() < 1e-9);
+            assert!((surface.iso_v(v).at(u) - surface.at((u, v))).norm() < 1e-9);
+            assert!((nurbs.iso_u(u).nurbs().at(v) - nurbs.at((u, v))).norm() < 1e-9);
+            assert!((nurbs.iso_v(v).nurbs().at(u) - nurbs.at((u, v))).norm() < 1e-9);
+        }
+        assert!(surface.iso_v(2.5).control_vec().wrapping());
+    }
+
+    #[test]
+    fn it_differentiates() {
+        use crate::splines::SplineDerivative;
+
+        let surface = wrapped(bumpy_points(4));
+        let nurbs = surface.nurbs();
+
+        // the partial derivatives are those of the iso curves through the point
+        for (u, v) in surface.quantize_range(0.25) {
+            let (du, dv) = surface.derivatives((u, v));
+            assert!((surface.iso_v(v).derivative(u) - du).norm() < 1e-9);
+            assert!((surface.iso_u(u).derivative(v) - dv).norm() < 1e-9);
+            let (du, dv) = nurbs.derivatives((u, v));
+            assert!((nurbs.iso_v(v).nurbs().derivative(u) - du).norm() < 1e-9);
+            assert!((nurbs.iso_u(u).nurbs().derivative(v) - dv).norm() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn it_swaps_and_reverses_directions() {
+        let surface = wrapped(bumpy_points(5).iter().map(|p| p.xyz()).collect());
+        let (u_start, u_end) = surface.u_range().into_inner();
+        let (v_start, v_end) = surface.v_range().into_inner();
+
+        let mut swapped = surface.clone();
+        swapped.swap_uv();
+        let mut reversed = surface.clone();
+        reversed.reverse_u();
+        reversed.reverse_v();
+        assert!(swapped.control_grid().v_wrapping());
+        assert_eq!(surface.u_range(), reversed.u_range());
+        for i in 0..=10 {
+            for j in 0..=10 {
+                let u = u_start + (u_end - u_start) * i as f64 / 10.;
+                let v = v_start + (v_end - v_start) * j as f64 / 10.;
+                let (back_u, back_v) = (u_start + u_end - u, v_start + v_end - v);
+                assert!((surface.at((u, v)) - swapped.at((v, u))).norm() < 1e-12);
+                assert!((surface.at((u, v)) - reversed.at((back_u, back_v))).norm() < 1e-12);
+            }
+        }
+    }
+
+    #[test]
+    fn it_extracts_subsurfaces() {
+        let surface = wrapped(bumpy_points(4).iter().map(|p| p.xyz()).collect());
+        let part = surface.subsurface(3.5..=5.25, 2.2..=3.7);
+
+        assert_eq!(3.5..=5.25, part.u_range());
+        assert_eq!(2.2..=3.7, part.v_range());
+        assert_eq!(surface.at((3.5, 2.2)), part.control_points()[0]);
+        for i in 0..=10 {
+            for j in 0..=10 {
+                let uv = (3.5 + 1.75 * i as f64 / 10., 2.2 + 1.5 * j as f64 / 10.);
+                assert!((surface.at(uv) - part.at(uv)).norm() < 1e-9);
+            }
+        }
+    }
 }
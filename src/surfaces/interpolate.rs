@@ -0,0 +1,198 @@
+use crate::algorithms::{averaged_knots, collocation, elevate, solve};
+use crate::bezier::Linear;
+use crate::control_points::{ControlGrid, Grid};
+use crate::splines::Parametrization;
+use crate::surfaces::BSurface;
+use crate::types::{Real, Vector};
+use alloc::vec::Vec;
+use nalgebra::allocator::Allocator;
+use nalgebra::{DefaultAllocator, Dim};
+
+impl<D: Dim, T: Real> BSurface<D, T>
+where
+    DefaultAllocator: Allocator<T, D>,
+{
+    /// Surface passing through a grid of points, with each row of `points` along u, over
+    /// the ranges zero to one. The points are spread out by `parametrization`, averaged
+    /// over every row and every column, and each row is interpolated by a curve of
+    /// `u_degree` before each column of the result by a curve of `v_degree`. As a surface
+    /// has one degree in both directions, the direction of lower degree is then elevated
+    /// to the higher degree, which keeps the surface the same.
+    /// `None` if the points cannot be interpolated, such as when repeated points get the
+    /// same parameter.
+    pub fn interpolate(
+        points: &Grid<Vector<D, T>>,
+        u_degree: usize,
+        v_degree: usize,
+        parametrization: Parametrization,
+    ) -> Option<Self> {
+        assert!(
+            u_degree > 0 && v_degree > 0,
+            "cannot interpolate with degree 0"
+        );
+        let (u_len, v_len) = (points.len(), points.height());
+        assert!(
+            u_len > u_degree && v_len > v_degree,
+            "not enough points for the degree"
+        );
+
+        let rows: Vec<&[Vector<D, T>]> = (0..v_len).map(|j| points.row(j)).collect();
+        let columns: Vec<Vec<Vector<D, T>>> = (0..u_len)
+            .map(|i| rows.iter().map(|row| row[i].clone()).collect())
+            .collect();
+        let u_parameters = average(
+            rows.iter()
+                .map(|row| parametrization.parameters(row, false)),
+        );
+        let v_parameters = average(
+            columns
+                .iter()
+                .map(|column| parametrization.parameters(column, false)),
+        );
+        let u_knots = averaged_knots(&u_parameters, u_degree);
+        let v_knots = averaged_knots(&v_parameters, v_degree);
+
+        // control points of curves along each row, then through those along each column
+        let u_matrix = collocation(&u_parameters, &u_knots, u_degree);
+        let rows = rows
+            .iter()
+            .map(|row| solve(u_matrix.clone(), row))
+            .collect::<Option<Vec<_>>>()?;
+        let v_matrix = collocation(&v_parameters, &v_knots, v_degree);
+        let columns = (0..u_len)
+            .map(|i| {
+                let column: Vec<_> = rows.iter().map(|row| row[i].clone()).collect();
+                solve(v_matrix.clone(), &column)
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        let degree = u_degree.max(v_degree);
+        let (v_knots, columns) = elevated(v_degree, degree, v_knots, columns);
+        let rows = (0..columns[0].len())
+            .map(|j| columns.iter().map(|column| column[j].clone()).collect())
+            .collect();
+        let (u_knots, rows) = elevated(u_degree, degree, u_knots, rows);
+        let u_count = rows[0].len();
+        Some(Self::with_knots(
+            ControlGrid::new(degree, u_count, rows.into_iter().flatten().collect()),
+            u_knots,
+            v_knots,
+        ))
+    }
+}
+
+/// Knots and control points of clamped splines of `degree` sharing `knots`, elevated
+/// to `target`.
+fn elevated<T: Real, P: Linear<T>>(
+    degree: usize,
+    target: usize,
+    knots: Vec<T>,
+    splines: Vec<Vec<P>>,
+) -> (Vec<T>, Vec<Vec<P>>) {
+    let mut elevated_knots = knots.clone();
+    let splines = splines
+        .into_iter()
+        .map(|points| {
+            let (knots, points) = (degree..target).fold((knots.clone(), points), |spline, d| {
+                elevate(d, spline.0, spline.1)
+            });
+            elevated_knots = knots;
+            points
+        })
+        .collect();
+    (elevated_knots, splines)
+}
+
+/// Average of lists of parameters of the same length.
+fn average<T: Real>(mut lists: impl Iterator<Item = Vec<T>>) -> Vec<T> {
+    let Some(mut sum) = lists.next() else {
+        return Vec::new();
+    };
+    let mut count = 1;
+    for list in lists {
+        for (total, value) in sum.iter_mut().zip(list) {
+            *total += value;
+        }
+        count += 1;
+    }
+    sum.iter_mut()
+        .for_each(|total| *total /= T::cast_from(count));
+    sum
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::surfaces::Surface;
+    use nalgebra::Vector3;
+
+    fn terrain() -> Grid<Vector3<f64>> {
+        let points = (0..5)
+            .flat_map(|j| {
+                (0..7).map(move |i| {
+                    let (x, y) = (i as f64 * (1. + i as f64 / 10.), j as f64);
+                    Vector3::new(x, y, x.sin() * y.cos())
+                })
+            })
+            .collect();
+        Grid::new(7, points)
+    }
+
+    #[test]
+    fn it_interpolates_grids() {
+        let points = terrain();
+        for parametrization in [Parametrization::Uniform, Parametrization::ChordLength] {
+            for degree in 1..=3 {
+                let surface =
+                    BSurface::interpolate(&points, degree, degree, parametrization).unwrap();
+                assert_eq!(0.0..=1.0, surface.u_range());
+                assert_eq!(0.0..=1.0, surface.v_range());
+                assert_eq!(35, surface.control_points().len());
+
+                let rows: Vec<_> = (0..5).map(|j| points.row(j)).collect();
+                let u = average(rows.iter().map(|r| parametrization.parameters(r, false)));
+                let columns: Vec<Vec<_>> = (0..7)
+                    .map(|i| rows.iter().map(|r| r[i]).collect())
+                    .collect();
+                let v = average(columns.iter().map(|c| parametrization.parameters(c, false)));
+                for j in 0..5 {
+                    for i in 0..7 {
+                        let point = surface.at((u[i], v[j]));
+                        assert!((point - points[(i, j)]).norm() < 1e-9);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn it_interpolates_with_different_degrees() {
+        let points = terrain();
+        let parametrization = Parametrization::Uniform;
+        for (u_degree, v_degree) in [(3, 1), (1, 3), (2, 3)] {
+            let surface =
+                BSurface::interpolate(&points, u_degree, v_degree, parametrization).unwrap();
+            assert_eq!(u_degree.max(v_degree), surface.degree());
+            for j in 0..5 {
+                for i in 0..7 {
+                    let (u, v) = (i as f64 / 6., j as f64 / 4.);
+                    assert!((surface.at((u, v)) - points[(i, j)]).norm() < 1e-9);
+                }
+            }
+
+            // linear between the rows or columns of points in the direction of degree one
+            for (i, j) in [(2, 1), (5, 3)] {
+                let (u, v) = (i as f64 / 6., j as f64 / 4.);
+                let (middle, neighbour) = if v_degree == 1 {
+                    (surface.at((u, v + 0.125)), points[(i, j + 1)])
+                } else if u_degree == 1 {
+                    (surface.at((u + 1. / 12., v)), points[(i + 1, j)])
+                } else {
+                    continue;
+                };
+                let expected = (points[(i, j)] + neighbour) / 2.;
+                assert!((middle - expected).norm() < 1e-9);
+            }
+        }
+    }
+}
@@ -1,5 +1,6 @@
-use crate::algorithms::cox_de_boor_uv;
-use crate::surfaces::{BSurface, Surface, UV};
+use crate::algorithms::{cox_de_boor_uv, project_derivative, unweight, weight};
+use crate::splines::BSpline;
+use crate::surfaces::{BSurface, Surface, SurfaceDerivatives, UV};
 use crate::types::{Scalar, Vector};
 use core::ops::RangeInclusive;
 use nalgebra::allocator::Allocator;
@@ -21,6 +22,31 @@ where
     pub(crate) fn new(spline: &'a BSurface<D, T>) -> Self {
         Self { spline }
     }
+
+    /// The weighted surface.
+    pub(crate) fn spline(&self) -> &'a BSurface<D, T> {
+        self.spline
+    }
+
+    /// Weighted spline of the NURBS curve along v at a fixed `u`.
+    pub fn iso_u(&self, u: T) -> BSpline<D, T> {
+        self.spline.iso_u_mapped(u, weight, unweight)
+    }
+
+    /// Weighted spline of the NURBS curve along u at a fixed `v`.
+    pub fn iso_v(&self, v: T) -> BSpline<D, T> {
+        self.spline.iso_v_mapped(v, weight, unweight)
+    }
+
+    /// Weighted surface of the NURBS covering `u_range` and `v_range`, which lie within the ranges of the NURBS.
+    pub fn subsurface(
+        &self,
+        u_range: RangeInclusive<T>,
+        v_range: RangeInclusive<T>,
+    ) -> BSurface<D, T> {
+        self.spline
+            .subsurface_mapped(u_range, v_range, weight, unweight)
+    }
 }
 
 impl<D: Dim + DimSub<U1>, T: Scalar> Surface<DimDiff<D, U1>, T> for NURBSurface<'_, D, T>
@@ -61,3 +87,20 @@ where
         lower
     }
 }
+
+impl<D: Dim + DimSub<U1>, T: Scalar> SurfaceDerivatives<DimDiff<D, U1>, T> for NURBSurface<'_, D, T>
+where
+    <D as DimSub<Const<1>>>::Output: DimName,
+    DefaultAllocator: Allocator<T, D>,
+    DefaultAllocator: Allocator<T, <D as DimSub<Const<1>>>::Output>,
+    <DefaultAllocator as Allocator<T, D>>::Buffer: Default,
+    <DefaultAllocator as Allocator<T, <D as DimSub<Const<1>>>::Output>>::Buffer: Default,
+{
+    fn derivatives(&self, uv: UV<T>) -> (Vector<DimDiff<D, U1>, T>, Vector<DimDiff<D, U1>, T>) {
+        let (point, du, dv) = self.spline.derivatives_mapped(uv, weight);
+        (
+            project_derivative(&point, &du),
+            project_derivative(&point, &dv),
+        )
+    }
+}
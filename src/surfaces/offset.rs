@@ -0,0 +1,294 @@
+use crate::control_points::ControlGrid;
+use crate::splines::approximate::least_squares;
+use crate::surfaces::{BSurface, NURBSurface, Surface, SurfaceDerivatives, UV};
+use crate::types::Real;
+use alloc::vec::Vec;
+use core::ops::RangeInclusive;
+use nalgebra::{Const, DVector, Vector3};
+
+/// Number of intervals between the samples fitted in each knot span, beyond the degree.
+const SAMPLES: usize = 2;
+/// Maximum number of rounds of halving the knot spans whose error is above the tolerance.
+const MAX_ROUNDS: usize = 5;
+
+impl<T: Real> BSurface<Const<3>, T> {
+    /// Surface offset by `distance` along its unit normal, approximated in the least squares
+    /// sense by a surface of the same degree with knots at the ends of the Bezier patches.
+    /// Knot spans are halved where the error, measured at points between the fitted samples,
+    /// is above `tolerance`. Returned with the largest error measured, which is above
+    /// `tolerance` if it is not reached within a fixed number of rounds of halving, or
+    /// `None` if a fit cannot be solved.
+    pub fn offset_surface(&self, distance: T, tolerance: T) -> Option<(Self, T)> {
+        offset_surface(self, self.degree(), breaks(self), distance, tolerance)
+    }
+}
+
+impl<T: Real> NURBSurface<'_, Const<4>, T> {
+    /// Surface offset by `distance` along its unit normal, approximated in the least squares
+    /// sense by a polynomial surface of the same degree with knots at the ends of the Bezier
+    /// patches. Knot spans are halved where the error, measured at points between the fitted
+    /// samples, is above `tolerance`. Returned with the largest error measured, which is
+    /// above `tolerance` if it is not reached within a fixed number of rounds of halving,
+    /// or `None` if a fit cannot be solved.
+    pub fn offset_surface(&self, distance: T, tolerance: T) -> Option<(BSurface<Const<3>, T>, T)> {
+        let spline = self.spline();
+        offset_surface(self, spline.degree(), breaks(spline), distance, tolerance)
+    }
+}
+
+/// Ends of the Bezier patches of a surface in each direction.
+fn breaks<const D: usize, T: Real>(surface: &BSurface<Const<D>, T>) -> UV<Vec<T>> {
+    let patches = surface.bezier_patches(Clone::clone);
+    let sorted = |mut breaks: Vec<T>| {
+        breaks.retain(|x| x.is_finite());
+        breaks.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        breaks.dedup();
+        breaks
+    };
+    (
+        sorted(patches.iter().flat_map(|p| [p.u.0, p.u.1]).collect()),
+        sorted(patches.iter().flat_map(|p| [p.v.0, p.v.1]).collect()),
+    )
+}
+
+fn offset_surface<T: Real>(
+    surface: &impl SurfaceDerivatives<Const<3>, T>,
+    degree: usize,
+    (mut u_breaks, mut v_breaks): UV<Vec<T>>,
+    distance: T,
+    tolerance: T,
+) -> Option<(BSurface<Const<3>, T>, T)> {
+    let degree = degree.max(1);
+    let point = |uv: UV<T>| surface.at(uv) + normal(surface, uv) * distance;
+    let count = degree + SAMPLES;
+
+    let mut round = 0;
+    loop {
+        let (us, vs) = (subdivide(&u_breaks, count), subdivide(&v_breaks, count));
+        let grid: Vec<_> = vs
+            .iter()
+            .flat_map(|&v| us.iter().map(move |&u| (u, v)))
+            .map(point)
+            .collect();
+        let knots = (knots(&u_breaks, degree), knots(&v_breaks, degree));
+        let fitted = fit(&grid, (&us, &vs), knots, degree)?;
+
+        // measure the error between the fitted samples and at the ends
+        let mut u_errors = alloc::vec![T::zero(); u_breaks.len() - 1];
+        let mut v_errors = alloc::vec![T::zero(); v_breaks.len() - 1];
+        let u_checks = checks(&u_breaks, 2 * count);
+        for &(v, j) in &checks(&v_breaks, 2 * count) {
+            for &(u, i) in &u_checks {
+                let error = (fitted.at((u, v)) - point((u, v))).norm();
+                u_errors[i] = u_errors[i].max(error);
+                v_errors[j] = v_errors[j].max(error);
+            }
+        }
+
+        let error = u_errors
+            .iter()
+            .chain(&v_errors)
+            .fold(T::zero(), |a, &b| a.max(b));
+        if error <= tolerance || round == MAX_ROUNDS {
+            return Some((fitted, error));
+        }
+
+        u_breaks = halve(&u_breaks, &u_errors, tolerance);
+        v_breaks = halve(&v_breaks, &v_errors, tolerance);
+        round += 1;
+    }
+}
+
+/// Least squares fit of a surface of `degree` with `knots` to the `grid` of points at `us`
+/// by `vs`. The columns of the grid are fitted along u at once, then the columns of control
+/// points along v, which gives the same surface as fitting the whole grid.
+fn fit<T: Real>(
+    grid: &[Vector3<T>],
+    (us, vs): UV<&[T]>,
+    (u_knots, v_knots): UV<Vec<T>>,
+    degree: usize,
+) -> Option<BSurface<Const<3>, T>> {
+    let columns: Vec<_> = (0..us.len())
+        .map(|i| {
+            let column = (0..vs.len()).flat_map(|j| grid[j * us.len() + i].iter().copied());
+            DVector::from_iterator(3 * vs.len(), column)
+        })
+        .collect();
+    let (columns, _) = least_squares(&columns, us, None, None, false, degree, u_knots.clone())?;
+    let u_count = columns.control_points().len();
+
+    let rows: Vec<_> = (0..vs.len())
+        .map(|j| {
+            let row = columns
+                .control_points()
+                .iter()
+                .flat_map(|c| c.rows(3 * j, 3).iter().copied().collect::<Vec<_>>());
+            DVector::from_iterator(3 * u_count, row)
+        })
+        .collect();
+    let (rows, _) = least_squares(&rows, vs, None, None, false, degree, v_knots.clone())?;
+    let control = rows
+        .control_points()
+        .iter()
+        .flat_map(|r| {
+            (0..u_count).map(|i| Vector3::from_iterator(r.rows(3 * i, 3).iter().copied()))
+        })
+        .collect();
+    Some(BSurface::with_knots(
+        ControlGrid::new(degree, u_count, control),
+        u_knots,
+        v_knots,
+    ))
+}
+
+/// Unit normal of `surface` at `uv`. Where the normal vanishes, such as at a pole,
+/// the normal of a nearby point towards the middle of the surface is used instead.
+fn normal<T: Real>(surface: &impl SurfaceDerivatives<Const<3>, T>, uv: UV<T>) -> Vector3<T> {
+    let (du, dv) = surface.derivatives(uv);
+    if let Some(normal) = du.cross(&dv).try_normalize(T::default_epsilon()) {
+        return normal;
+    }
+    let nudge = |x: T, range: RangeInclusive<T>| {
+        let (start, end) = range.into_inner();
+        let h = (end - start) * T::default_epsilon().sqrt();
+        if x + h <= end {
+            x + h
+        } else {
+            x - h
+        }
+    };
+    let (du, dv) = surface.derivatives((
+        nudge(uv.0, surface.u_range()),
+        nudge(uv.1, surface.v_range()),
+    ));
+    du.cross(&dv).normalize()
+}
+
+/// Clamped knots of `degree` with single knots at the inner `breaks`.
+fn knots<T: Real>(breaks: &[T], degree: usize) -> Vec<T> {
+    let mut knots = alloc::vec![breaks[0]; degree];
+    knots.extend_from_slice(breaks);
+    knots.extend(core::iter::repeat_n(breaks[breaks.len() - 1], degree));
+    knots
+}
+
+/// Points halfway between those of `breaks` subdivided into `count` intervals, together
+/// with the ends of `breaks`, each with the index of the interval of `breaks` holding it.
+fn checks<T: Real>(breaks: &[T], count: usize) -> Vec<(T, usize)> {
+    let last = breaks.len() - 2;
+    let mut values = Vec::from([(breaks[0], 0)]);
+    for (index, span) in breaks.windows(2).enumerate() {
+        values.extend((0..count).map(|i| {
+            let x = T::cast_from(2 * i + 1) / T::cast_from(2 * count);
+            (span[0] + (span[1] - span[0]) * x, index)
+        }));
+    }
+    values.push((breaks[last + 1], last));
+    values
+}
+
+/// Each interval between `breaks` split evenly into `count` intervals.
+fn subdivide<T: Real>(breaks: &[T], count: usize) -> Vec<T> {
+    let mut values = Vec::from([breaks[0]]);
+    for span in breaks.windows(2) {
+        values.extend(
+            (1..=count)
+                .map(|i| span[0] + (span[1] - span[0]) * T::cast_from(i) / T::cast_from(count)),
+        );
+    }
+    values
+}
+
+/// Halve the intervals whose error exceeds `tolerance`.
+fn halve<T: Real>(values: &[T], errors: &[T], tolerance: T) -> Vec<T> {
+    let mut halved = Vec::from([values[0]]);
+    for (span, &error) in values.windows(2).zip(errors) {
+        if error > tolerance {
+            halved.push((span[0] + span[1]) / T::cast_from(2));
+        }
+        halved.push(span[1]);
+    }
+    halved
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    #[test]
+    fn it_offsets_planes() {
+        let mut plane = BSurface::new(ControlGrid::new(
+            1,
+            2,
+            vec![
+                Vector3::new(0f64, 0., 0.),
+                Vector3::new(2., 0., 0.),
+                Vector3::new(0., 1., 0.),
+                Vector3::new(2., 1., 0.),
+            ],
+        ));
+        plane.u_knots_mut().clamp_ends();
+        plane.v_knots_mut().clamp_ends();
+        let (offset, error) = plane.offset_surface(0.5, 1e-6).unwrap();
+
+        assert!(error < 1e-12);
+        assert_eq!(plane.u_range(), offset.u_range());
+        for (u, v) in [(1., 1.), (1.5, 1.25), (2., 2.)] {
+            assert!((offset.at((u, v)) - plane.at((u, v)) - Vector3::z() * 0.5).norm() < 1e-12);
+        }
+    }
+
+    fn saddle() -> BSurface<Const<3>, f64> {
+        let points = (0..4)
+            .flat_map(|j| {
+                (0..4).map(move |i| {
+                    let (x, y) = (i as f64, j as f64);
+                    Vector3::new(x, y, ((x - 1.5) * (y - 1.5)) / 2.)
+                })
+            })
+            .collect();
+        let mut surface = BSurface::new(ControlGrid::new(2, 4, points));
+        surface.u_knots_mut().clamp_ends();
+        surface.v_knots_mut().clamp_ends();
+        surface
+    }
+
+    #[test]
+    fn it_offsets_curved_surfaces() {
+        let surface = saddle();
+        let distance = 0.3;
+        let (offset, error) = surface.offset_surface(distance, 1e-3).unwrap();
+
+        assert!(error <= 1e-3);
+        assert_eq!(surface.degree(), offset.degree());
+        let (start, end) = surface.u_range().into_inner();
+        for j in 0..=20 {
+            for i in 0..=20 {
+                let u = start + (end - start) * i as f64 / 20.;
+                let v = start + (end - start) * j as f64 / 20.;
+                let (du, dv) = surface.derivatives((u, v));
+                let exact = surface.at((u, v)) + du.cross(&dv).normalize() * distance;
+                assert!((offset.at((u, v)) - exact).norm() < 2e-3);
+            }
+        }
+    }
+
+    #[test]
+    fn it_reports_unmet_tolerances() {
+        let mut twisted = BSurface::new(ControlGrid::new(
+            1,
+            2,
+            vec![
+                Vector3::new(0f64, 0., 0.),
+                Vector3::new(1., 0., 0.),
+                Vector3::new(0., 1., 0.),
+                Vector3::new(1., 1., 1.),
+            ],
+        ));
+        twisted.u_knots_mut().clamp_ends();
+        twisted.v_knots_mut().clamp_ends();
+        let (_, error) = twisted.offset_surface(0.5, 1e-15).unwrap();
+        assert!(error > 1e-15);
+    }
+}
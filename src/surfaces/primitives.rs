@@ -0,0 +1,223 @@
+use crate::control_points::ControlGrid;
+use crate::splines::BSpline;
+use crate::surfaces::BSurface;
+use crate::types::Real;
+use alloc::vec::Vec;
+use core::ops::RangeInclusive;
+use nalgebra::{Const, Vector2, Vector3, Vector4};
+
+impl<T: Real> BSurface<Const<4>, T> {
+    /// Weighted surface of the NURBS parallelogram from `origin` spanned by `x_axis` along u
+    /// and `y_axis` along v, over the ranges zero to one.
+    pub fn plane(origin: &Vector3<T>, x_axis: &Vector3<T>, y_axis: &Vector3<T>) -> Self {
+        let one = T::cast_from(1);
+        let control = Vec::from([
+            origin.push(one),
+            (origin + x_axis).push(one),
+            (origin + y_axis).push(one),
+            (origin + x_axis + y_axis).push(one),
+        ]);
+        let knots = Vec::from([T::zero(), T::zero(), one, one]);
+        Self::with_knots(ControlGrid::new(1, 2, control), knots.clone(), knots)
+    }
+
+    /// Weighted surface of the NURBS cylinder of `radius` around the z axis, from the xy
+    /// plane up to `height`. The surface goes around the axis along u over `angles` in
+    /// radians from the x axis towards the y axis, and up along v, over the ranges zero to one.
+    pub fn cylinder(radius: T, height: T, angles: RangeInclusive<T>) -> Self {
+        Self::cone(radius, radius, height, angles)
+    }
+
+    /// Weighted surface of the NURBS cone around the z axis, going from `bottom_radius` in
+    /// the xy plane to `top_radius` at `height`, either of which may be zero. The surface
+    /// goes around the axis along u over `angles` in radians from the x axis towards the
+    /// y axis, and up along v, over the ranges zero to one.
+    pub fn cone(bottom_radius: T, top_radius: T, height: T, angles: RangeInclusive<T>) -> Self {
+        let bottom = Vector2::new(bottom_radius, T::zero());
+        let top = Vector2::new(top_radius, height);
+        let middle = (bottom + top) / T::cast_from(2);
+        revolve(
+            &BSpline::conic_arc(&bottom, &middle, &top, T::cast_from(1)),
+            angles,
+        )
+    }
+
+    /// Weighted surface of the NURBS sphere of `radius` around the origin. The surface
+    /// goes around the z axis along u over `angles` in radians from the x axis towards the
+    /// y axis, and up along v over `latitudes` in radians from minus a quarter turn at the
+    /// bottom to a quarter turn at the top, over the ranges zero to one.
+    pub fn sphere(radius: T, angles: RangeInclusive<T>, latitudes: RangeInclusive<T>) -> Self {
+        let (start, end) = latitudes.into_inner();
+        let quarter = T::frac_pi_2();
+        assert!(
+            -quarter <= start && end <= quarter,
+            "latitudes from the bottom to the top"
+        );
+        let meridian = BSpline::arc(
+            &Vector2::zeros(),
+            &Vector2::x(),
+            &Vector2::y(),
+            radius,
+            start,
+            end,
+        );
+        revolve(&meridian, angles)
+    }
+
+    /// Weighted surface of the NURBS torus around the z axis, with a tube of `minor_radius`
+    /// around a circle of `major_radius` in the xy plane. The surface goes around the z
+    /// axis along u over `angles` in radians from the x axis towards the y axis, and around
+    /// the tube along v over `tube_angles` in radians from the outside towards the top,
+    /// over the ranges zero to one.
+    pub fn torus(
+        major_radius: T,
+        minor_radius: T,
+        angles: RangeInclusive<T>,
+        tube_angles: RangeInclusive<T>,
+    ) -> Self {
+        assert!(
+            minor_radius <= major_radius,
+            "the tube cannot cross the axis"
+        );
+        let (start, end) = tube_angles.into_inner();
+        let tube = BSpline::arc(
+            &Vector2::new(major_radius, T::zero()),
+            &Vector2::x(),
+            &Vector2::y(),
+            minor_radius,
+            start,
+            end,
+        );
+        revolve(&tube, angles)
+    }
+}
+
+/// Weighted surface of the NURBS surface of revolution of the quadratic `profile`, whose
+/// points are given by their distance from the z axis and their height, sweeping around
+/// the z axis over `angles` along u while following the profile along v.
+fn revolve<T: Real>(
+    profile: &BSpline<Const<3>, T>,
+    angles: RangeInclusive<T>,
+) -> BSurface<Const<4>, T> {
+    let (start, end) = angles.into_inner();
+    let circle: BSpline<Const<3>, T> = BSpline::arc(
+        &Vector2::zeros(),
+        &Vector2::x(),
+        &Vector2::y(),
+        T::cast_from(1),
+        start,
+        end,
+    );
+
+    // the homogeneous points are products of those of the circle and the profile
+    let control = profile
+        .control_points()
+        .iter()
+        .flat_map(|p| {
+            circle
+                .control_points()
+                .iter()
+                .map(move |c| Vector4::new(p.x * c.x, p.x * c.y, p.y, p.z * c.z))
+        })
+        .collect();
+    BSurface::with_knots(
+        ControlGrid::new(2, circle.control_points().len(), control),
+        knot_vec(&circle),
+        knot_vec(profile),
+    )
+}
+
+/// All the knots of a quadratic spline.
+fn knot_vec<T: Real>(spline: &BSpline<Const<3>, T>) -> Vec<T> {
+    let knots = spline.knots();
+    (0..spline.control_vec().len() + 3)
+        .map(|i| knots[i])
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::surfaces::{Surface, SurfaceDerivatives};
+    use core::f64::consts::{FRAC_PI_2, PI};
+
+    /// Points of the NURBS over a grid of coordinates.
+    fn samples(surface: &BSurface<Const<4>, f64>) -> Vec<Vector3<f64>> {
+        let nurbs = surface.nurbs();
+        (0..=20)
+            .flat_map(|j| (0..=20).map(move |i| (i as f64 / 20., j as f64 / 20.)))
+            .map(|uv| nurbs.at(uv))
+            .collect()
+    }
+
+    #[test]
+    fn it_makes_planes() {
+        let plane = BSurface::plane(
+            &Vector3::new(1., 2., 3.),
+            &Vector3::new(2., 0., 0.),
+            &Vector3::new(0., 1., 1.),
+        );
+        assert_eq!(0.0..=1.0, plane.u_range());
+        let p = plane.nurbs().at((0.5, 0.25));
+        assert!((p - Vector3::new(2., 2.25, 3.25)).norm() < 1e-12);
+    }
+
+    #[test]
+    fn it_makes_cylinders_and_cones() {
+        let cylinder = BSurface::cylinder(2., 3., 0.0..=2. * PI);
+        for p in samples(&cylinder) {
+            assert!((p.xy().norm() - 2.).abs() < 1e-12);
+            assert!(p.z >= -1e-12 && p.z <= 3. + 1e-12);
+        }
+        let nurbs = cylinder.nurbs();
+        assert!((nurbs.at((0.25, 0.5)) - Vector3::new(0., 2., 1.5)).norm() < 1e-12);
+        // the normal points outwards
+        let (du, dv) = nurbs.derivatives((0., 0.5));
+        assert!(du.cross(&dv).x > 0.);
+
+        let cone = BSurface::cone(2., 0., 4., 0.0..=FRAC_PI_2);
+        for p in samples(&cone) {
+            assert!((p.xy().norm() - (2. - p.z / 2.)).abs() < 1e-12);
+            assert!(p.x >= -1e-12 && p.y >= -1e-12);
+        }
+        assert!((cone.nurbs().at((1., 1.)) - Vector3::new(0., 0., 4.)).norm() < 1e-12);
+    }
+
+    #[test]
+    fn it_makes_spheres() {
+        let sphere = BSurface::sphere(1.5, 0.0..=2. * PI, -FRAC_PI_2..=FRAC_PI_2);
+        for p in samples(&sphere) {
+            assert!((p.norm() - 1.5).abs() < 1e-12);
+        }
+        let nurbs = sphere.nurbs();
+        assert!((nurbs.at((0.3, 1.)) - Vector3::new(0., 0., 1.5)).norm() < 1e-12);
+        let (du, dv) = nurbs.derivatives((0.5, 0.5));
+        let p = nurbs.at((0.5, 0.5));
+        assert!(du.cross(&dv).dot(&p) > 0.);
+
+        // a band around the equator of a half sphere
+        let band = BSurface::sphere(1., 0.0..=PI, -0.5..=0.5);
+        for p in samples(&band) {
+            assert!((p.norm() - 1.).abs() < 1e-12);
+            assert!(p.z.abs() <= 0.5f64.sin() + 1e-12);
+            assert!(p.y >= -1e-12);
+        }
+    }
+
+    #[test]
+    fn it_makes_tori() {
+        let torus = BSurface::torus(3., 1., 0.0..=2. * PI, 0.0..=2. * PI);
+        for p in samples(&torus) {
+            let tube = Vector2::new(p.xy().norm() - 3., p.z);
+            assert!((tube.norm() - 1.).abs() < 1e-12);
+        }
+        let nurbs = torus.nurbs();
+        assert!((nurbs.at((0., 0.)) - Vector3::new(4., 0., 0.)).norm() < 1e-12);
+        assert!((nurbs.at((0.5, 0.5)) - Vector3::new(-2., 0., 0.)).norm() < 1e-12);
+
+        let quarter = BSurface::torus(3., 1., 0.0..=FRAC_PI_2, 0.0..=PI);
+        for p in samples(&quarter) {
+            assert!(p.x >= -1e-12 && p.y >= -1e-12 && p.z >= -1e-12);
+        }
+    }
+}
@@ -0,0 +1,313 @@
+use crate::algorithms::weight;
+use crate::splines::{BSpline, Spline};
+use crate::surfaces::UV;
+use crate::types::Real;
+use alloc::vec::Vec;
+use core::ops::RangeInclusive;
+use nalgebra::{Const, Vector2, Vector3};
+
+/// Number of line segments each Bezier segment of a loop is flattened into.
+const FLATTEN: usize = 32;
+
+/// 2D curve of a trimming loop in the uv space of a surface.
+#[derive(Debug, Clone)]
+pub enum TrimCurve<T: Real> {
+    /// Polynomial curve.
+    Polynomial(BSpline<Const<2>, T>),
+    /// Weighted spline of a NURBS curve, whose last coordinate is the weight.
+    Rational(BSpline<Const<3>, T>),
+}
+
+impl<T: Real> From<BSpline<Const<2>, T>> for TrimCurve<T> {
+    fn from(curve: BSpline<Const<2>, T>) -> Self {
+        Self::Polynomial(curve)
+    }
+}
+
+impl<T: Real> From<BSpline<Const<3>, T>> for TrimCurve<T> {
+    fn from(curve: BSpline<Const<3>, T>) -> Self {
+        Self::Rational(curve)
+    }
+}
+
+impl<T: Real> Spline<Const<2>, T> for TrimCurve<T> {
+    fn range(&self) -> RangeInclusive<T> {
+        match self {
+            Self::Polynomial(curve) => curve.range(),
+            Self::Rational(curve) => curve.range(),
+        }
+    }
+
+    fn at(&self, t: T) -> Vector2<T> {
+        match self {
+            Self::Polynomial(curve) => curve.at(t),
+            Self::Rational(curve) => curve.nurbs().at(t),
+        }
+    }
+}
+
+/// Surface restricted to the region of its domain within an outer loop and outside any holes.
+/// Loops are made of 2D curves in the uv space of the surface, each starting where the previous
+/// ends and the last ending where the first starts.
+#[derive(Debug, Clone)]
+pub struct TrimmedSurface<S, T: Real> {
+    surface: S,
+    outer: Vec<TrimCurve<T>>,
+    holes: Vec<Vec<TrimCurve<T>>>,
+    polygons: Vec<Vec<Vector2<T>>>,
+}
+
+impl<S, T: Real> TrimmedSurface<S, T> {
+    /// Trim `surface` to the region inside the `outer` loop.
+    /// Panics if the curves of the loop do not connect up into a closed loop.
+    pub fn new(surface: S, outer: Vec<impl Into<TrimCurve<T>>>) -> Self {
+        let outer = closed_loop(outer);
+        let polygons = Vec::from([flatten(&outer)]);
+        Self {
+            surface,
+            outer,
+            holes: Vec::new(),
+            polygons,
+        }
+    }
+
+    /// Cut a hole in the region inside the `hole` loop.
+    /// Panics if the curves of the loop do not connect up into a closed loop.
+    pub fn add_hole(&mut self, hole: Vec<impl Into<TrimCurve<T>>>) {
+        let hole = closed_loop(hole);
+        self.polygons.push(flatten(&hole));
+        self.holes.push(hole);
+    }
+
+    /// The untrimmed surface.
+    pub fn surface(&self) -> &S {
+        &self.surface
+    }
+
+    /// Curves of the outer loop.
+    pub fn outer(&self) -> &[TrimCurve<T>] {
+        &self.outer
+    }
+
+    /// Curves of each hole loop.
+    pub fn holes(&self) -> &[Vec<TrimCurve<T>>] {
+        &self.holes
+    }
+
+    /// If `uv` is inside the trimmed region, tested against finely flattened loops.
+    pub fn contains(&self, (u, v): UV<T>) -> bool {
+        let point = Vector2::new(u, v);
+        let (outer, holes) = self.polygons.split_first().unwrap();
+        inside(outer, &point) && !holes.iter().any(|hole| inside(hole, &point))
+    }
+
+    /// Points along each loop, outer first, at most `step` apart in uv space.
+    /// The ends of every curve are included so that corners are kept.
+    pub(crate) fn boundary(&self, step: T) -> Vec<Vec<Vector2<T>>> {
+        core::iter::once(&self.outer)
+            .chain(&self.holes)
+            .map(|curves| {
+                curves
+                    .iter()
+                    .flat_map(|curve| {
+                        let mut points = resample(&flatten_curve(curve), step);
+                        points.pop();
+                        points
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+/// The curves of a loop, checking that each starts where the previous ends, the first where
+/// the last ends, to within a small fraction of the size of the loop.
+fn closed_loop<T: Real>(curves: Vec<impl Into<TrimCurve<T>>>) -> Vec<TrimCurve<T>> {
+    let curves: Vec<TrimCurve<T>> = curves.into_iter().map(Into::into).collect();
+    assert!(!curves.is_empty(), "loops need at least one curve");
+    let ends: Vec<_> = curves
+        .iter()
+        .map(|curve| {
+            let (start, end) = curve.range().into_inner();
+            (curve.at(start), curve.at(end))
+        })
+        .collect();
+    let size = ends
+        .iter()
+        .flat_map(|(start, end)| [start, end])
+        .map(|p| (p - ends[0].0).norm())
+        .fold(T::cast_from(1), T::max);
+    let tolerance = size * T::default_epsilon().sqrt();
+
+    for pair in ends.windows(2) {
+        assert!(
+            (pair[1].0 - pair[0].1).norm() <= tolerance,
+            "each curve of a loop must start where the previous ends"
+        );
+    }
+    assert!(
+        (ends[0].0 - ends[ends.len() - 1].1).norm() <= tolerance,
+        "loops must end where they start"
+    );
+    curves
+}
+
+/// Closed polygon following a loop of curves.
+fn flatten<T: Real>(curves: &[TrimCurve<T>]) -> Vec<Vector2<T>> {
+    curves
+        .iter()
+        .flat_map(|curve| {
+            let mut points = flatten_curve(curve);
+            points.pop();
+            points
+        })
+        .collect()
+}
+
+/// Polyline following a curve, through both of its ends.
+fn flatten_curve<T: Real>(curve: &TrimCurve<T>) -> Vec<Vector2<T>> {
+    let segments = match curve {
+        TrimCurve::Polynomial(curve) => curve.bezier_segments(|p| p.push(T::cast_from(1))),
+        TrimCurve::Rational(curve) => curve.bezier_segments(weight),
+    };
+    let project = |p: Vector3<T>| p.xy() / p.z;
+    let mut points: Vec<_> = segments
+        .iter()
+        .flat_map(|segment| {
+            (0..FLATTEN).map(|i| project(segment.at(T::cast_from(i) / T::cast_from(FLATTEN))))
+        })
+        .collect();
+    if let Some(last) = segments.last() {
+        points.push(project(last.at(T::cast_from(1))));
+    }
+    points
+}
+
+/// Points evenly spaced by length along a polyline, at most `step` apart.
+fn resample<T: Real>(polyline: &[Vector2<T>], step: T) -> Vec<Vector2<T>> {
+    let lengths: Vec<_> = polyline.windows(2).map(|w| (w[1] - w[0]).norm()).collect();
+    let total = lengths.iter().fold(T::zero(), |a, &b| a + b);
+    let count: usize = (total / step).ceil().cast();
+    let count = count.max(1);
+
+    let mut points = Vec::with_capacity(count + 1);
+    let (mut segment, mut start) = (0, T::zero());
+    for i in 0..=count {
+        let at = total * T::cast_from(i) / T::cast_from(count);
+        while segment < lengths.len() - 1 && start + lengths[segment] < at {
+            start += lengths[segment];
+            segment += 1;
+        }
+        let t = if lengths[segment] > T::zero() {
+            ((at - start) / lengths[segment]).clamp(T::zero(), T::cast_from(1))
+        } else {
+            T::zero()
+        };
+        points.push(polyline[segment] + (polyline[segment + 1] - polyline[segment]) * t);
+    }
+    points
+}
+
+/// If `point` is inside the closed `polygon` by counting the crossings of a ray along u.
+fn inside<T: Real>(polygon: &[Vector2<T>], point: &Vector2<T>) -> bool {
+    let mut inside = false;
+    for i in 0..polygon.len() {
+        let (a, b) = (polygon[i], polygon[(i + 1) % polygon.len()]);
+        if (a.y > point.y) != (b.y > point.y) {
+            let x = a.x + (point.y - a.y) / (b.y - a.y) * (b.x - a.x);
+            if point.x < x {
+                inside = !inside;
+            }
+        }
+    }
+    inside
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::control_points::ControlVec;
+    use alloc::vec;
+
+    fn square(min: f64, max: f64) -> Vec<BSpline<Const<2>, f64>> {
+        let corners = [(min, min), (max, min), (max, max), (min, max)];
+        (0..4)
+            .map(|i| {
+                let (a, b) = (corners[i], corners[(i + 1) % 4]);
+                let mut line = BSpline::new(ControlVec::new(
+                    1,
+                    vec![Vector2::new(a.0, a.1), Vector2::new(b.0, b.1)],
+                ));
+                line.knots_mut().clamp_ends();
+                line
+            })
+            .collect()
+    }
+
+    #[test]
+    fn it_contains_points_between_loops() {
+        let mut trimmed = TrimmedSurface::new((), square(1., 3.));
+        trimmed.add_hole(square(1.5, 2.5));
+
+        assert!(trimmed.contains((1.2, 1.2)));
+        assert!(trimmed.contains((2.8, 2.)));
+        assert!(!trimmed.contains((2., 2.)));
+        assert!(!trimmed.contains((0.5, 2.)));
+        assert!(!trimmed.contains((3.5, 3.5)));
+    }
+
+    #[test]
+    fn it_trims_with_rational_loops() {
+        let circle: BSpline<Const<3>, f64> = BSpline::arc(
+            &Vector2::new(2., 2.),
+            &Vector2::x(),
+            &Vector2::y(),
+            1.,
+            0.,
+            2. * core::f64::consts::PI,
+        );
+        let mut trimmed = TrimmedSurface::new((), square(0., 4.));
+        trimmed.add_hole(vec![circle]);
+
+        assert!(trimmed.contains((2.75, 2.7)));
+        assert!(!trimmed.contains((2.65, 2.65)));
+        assert!(!trimmed.contains((2., 2.95)));
+        for p in &trimmed.boundary(0.1)[1] {
+            assert!(((p - Vector2::new(2., 2.)).norm() - 1.).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "each curve of a loop must start where the previous ends")]
+    fn it_rejects_disconnected_loops() {
+        let mut curves = square(1., 3.);
+        curves.swap(1, 2);
+        TrimmedSurface::new((), curves);
+    }
+
+    #[test]
+    #[should_panic(expected = "loops must end where they start")]
+    fn it_rejects_open_loops() {
+        let mut curves = square(1., 3.);
+        curves.pop();
+        TrimmedSurface::new((), curves);
+    }
+
+    #[test]
+    fn it_samples_boundaries() {
+        let mut trimmed = TrimmedSurface::new((), square(1., 3.));
+        trimmed.add_hole(square(1.5, 2.5));
+        let boundary = trimmed.boundary(0.3);
+
+        assert_eq!(2, boundary.len());
+        assert_eq!(4 * 7, boundary[0].len());
+        assert_eq!(4 * 4, boundary[1].len());
+        for loop_ in &boundary {
+            for i in 0..loop_.len() {
+                let gap = (loop_[(i + 1) % loop_.len()] - loop_[i]).norm();
+                assert!(gap <= 0.3 + 1e-12);
+            }
+        }
+        assert!(boundary[0].contains(&Vector2::new(3., 3.)));
+    }
+}
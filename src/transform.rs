@@ -0,0 +1,321 @@
+use crate::algorithms::{unweight, weight};
+use crate::splines::{BSpline, NURBSpline};
+use crate::surfaces::{BSurface, NURBSurface};
+use crate::types::Real;
+use nalgebra::allocator::Allocator;
+use nalgebra::{
+    Const, DefaultAllocator, DimNameAdd, DimNameSum, OMatrix, Rotation, SMatrix, SVector, U1,
+};
+
+/// Homogeneous matrix of an affine map of `D` dimensional points.
+type Affine<const D: usize, T> = OMatrix<T, DimNameSum<Const<D>, U1>, DimNameSum<Const<D>, U1>>;
+
+/// Apply the affine `matrix` to a point, whose projective row must be zero apart from a one
+/// on its diagonal.
+fn affine<const D: usize, T: Real>(matrix: &Affine<D, T>, point: &SVector<T, D>) -> SVector<T, D>
+where
+    Const<D>: DimNameAdd<U1>,
+    DefaultAllocator: Allocator<T, DimNameSum<Const<D>, U1>, DimNameSum<Const<D>, U1>>,
+{
+    debug_assert!(
+        (0..D).all(|i| matrix[(D, i)] == T::zero()) && matrix[(D, D)] == T::cast_from(1),
+        "the last row of an affine matrix is zero apart from a one on its diagonal"
+    );
+    matrix.fixed_view::<D, D>(0, 0) * point + matrix.fixed_view::<D, 1>(0, D)
+}
+
+/// Apply the projective `matrix` to an unweighted NURBS point through its homogeneous form.
+fn projective<const D: usize, T: Real>(
+    matrix: &SMatrix<T, D, D>,
+    point: &SVector<T, D>,
+) -> SVector<T, D> {
+    unweight(&(matrix * weight(point)))
+}
+
+/// Reflect a point in the plane through the origin perpendicular to `normal`.
+fn reflect<const D: usize, T: Real>(
+    normal: &SVector<T, D>,
+    point: &SVector<T, D>,
+) -> SVector<T, D> {
+    point - normal * (T::cast_from(2) * point.dot(normal) / normal.norm_squared())
+}
+
+macro_rules! impl_transforms {
+    ($type:ident, $points:ident) => {
+        impl<const D: usize, T: Real> $type<Const<D>, T> {
+            /// Translate every control point by `offset`.
+            pub fn translate(&mut self, offset: &SVector<T, D>) {
+                for p in self.$points() {
+                    *p += offset;
+                }
+            }
+
+            /// Rotate every control point about the origin.
+            pub fn rotate(&mut self, rotation: &Rotation<T, D>) {
+                for p in self.$points() {
+                    *p = rotation * *p;
+                }
+            }
+
+            /// Scale every control point away from the origin by `factor` along each axis.
+            pub fn scale(&mut self, factor: &SVector<T, D>) {
+                for p in self.$points() {
+                    p.component_mul_assign(factor);
+                }
+            }
+
+            /// Mirror every control point in the plane through the origin perpendicular to `normal`.
+            pub fn mirror(&mut self, normal: &SVector<T, D>) {
+                for p in self.$points() {
+                    *p = reflect(normal, p);
+                }
+            }
+        }
+
+        impl<const D: usize, T: Real> $type<Const<D>, T>
+        where
+            Const<D>: DimNameAdd<U1>,
+            DefaultAllocator: Allocator<T, DimNameSum<Const<D>, U1>, DimNameSum<Const<D>, U1>>,
+        {
+            /// Apply the affine map of the homogeneous `matrix` to every control point,
+            /// whose last row must be zero apart from a one on its diagonal.
+            pub fn transform(&mut self, matrix: &Affine<D, T>) {
+                for p in self.$points() {
+                    *p = affine(matrix, p);
+                }
+            }
+
+            /// Copy with the affine map of the homogeneous `matrix` applied,
+            /// whose last row must be zero apart from a one on its diagonal.
+            pub fn transformed(&self, matrix: &Affine<D, T>) -> Self {
+                let mut transformed = self.clone();
+                transformed.transform(matrix);
+                transformed
+            }
+        }
+    };
+}
+
+impl_transforms!(BSpline, control_points_mut);
+impl_transforms!(BSurface, control_points_mut);
+
+impl<const D: usize, T: Real> NURBSpline<'_, Const<D>, T> {
+    /// Weighted spline of the NURBS mapped by the projective `matrix`, which acts on homogeneous
+    /// coordinates where the weight is last. Affine maps leave the weights unchanged, other
+    /// maps also update the weights so that the whole curve is mapped exactly.
+    pub fn transformed(&self, matrix: &SMatrix<T, D, D>) -> BSpline<Const<D>, T> {
+        let mut transformed = self.spline().clone();
+        for p in transformed.control_points_mut() {
+            *p = projective(matrix, p);
+        }
+        transformed
+    }
+}
+
+impl<const D: usize, T: Real> NURBSurface<'_, Const<D>, T> {
+    /// Weighted surface of the NURBS mapped by the projective `matrix`, which acts on homogeneous
+    /// coordinates where the weight is last. Affine maps leave the weights unchanged, other
+    /// maps also update the weights so that the whole surface is mapped exactly.
+    pub fn transformed(&self, matrix: &SMatrix<T, D, D>) -> BSurface<Const<D>, T> {
+        let mut transformed = self.spline().clone();
+        for p in transformed.control_points_mut() {
+            *p = projective(matrix, p);
+        }
+        transformed
+    }
+}
+
+macro_rules! impl_nurbs_transforms {
+    ($type:ident, $weighted:ident, $dim:literal) => {
+        impl<T: Real> $type<'_, Const<{ $dim + 1 }>, T> {
+            /// Weighted copy with `f` applied to the point of every control point, keeping
+            /// its weight.
+            fn mapped(
+                &self,
+                f: impl Fn(&SVector<T, $dim>) -> SVector<T, $dim>,
+            ) -> $weighted<Const<{ $dim + 1 }>, T> {
+                let mut mapped = self.spline().clone();
+                for p in mapped.control_points_mut() {
+                    let point = f(&p.fixed_rows::<$dim>(0).into_owned());
+                    p.fixed_rows_mut::<$dim>(0).copy_from(&point);
+                }
+                mapped
+            }
+
+            /// Weighted copy of the NURBS translated by `offset`.
+            pub fn translated(
+                &self,
+                offset: &SVector<T, $dim>,
+            ) -> $weighted<Const<{ $dim + 1 }>, T> {
+                self.mapped(|p| p + offset)
+            }
+
+            /// Weighted copy of the NURBS rotated about the origin.
+            pub fn rotated(
+                &self,
+                rotation: &Rotation<T, $dim>,
+            ) -> $weighted<Const<{ $dim + 1 }>, T> {
+                self.mapped(|p| rotation * p)
+            }
+
+            /// Weighted copy of the NURBS scaled away from the origin by `factor` along each axis.
+            pub fn scaled(&self, factor: &SVector<T, $dim>) -> $weighted<Const<{ $dim + 1 }>, T> {
+                self.mapped(|p| p.component_mul(factor))
+            }
+
+            /// Weighted copy of the NURBS mirrored in the plane through the origin perpendicular
+            /// to `normal`.
+            pub fn mirrored(&self, normal: &SVector<T, $dim>) -> $weighted<Const<{ $dim + 1 }>, T> {
+                self.mapped(|p| reflect(normal, p))
+            }
+
+            /// Weighted copy of the NURBS with the affine map of the homogeneous `matrix` applied
+            /// to its points, whose last row must be zero apart from a one on its diagonal.
+            pub fn affine_transformed(
+                &self,
+                matrix: &Affine<$dim, T>,
+            ) -> $weighted<Const<{ $dim + 1 }>, T> {
+                self.mapped(|p| affine(matrix, p))
+            }
+        }
+    };
+}
+
+impl_nurbs_transforms!(NURBSpline, BSpline, 2);
+impl_nurbs_transforms!(NURBSpline, BSpline, 3);
+impl_nurbs_transforms!(NURBSurface, BSurface, 2);
+impl_nurbs_transforms!(NURBSurface, BSurface, 3);
+
+#[cfg(test)]
+mod tests {
+    use crate::control_points::{ControlGrid, ControlVec};
+    use crate::splines::{BSpline, Spline};
+    use crate::surfaces::{BSurface, Surface};
+    use alloc::vec;
+    use core::f64::consts::PI;
+    use nalgebra::{Matrix3, Matrix4, Rotation2, Vector2, Vector3};
+
+    #[test]
+    fn it_transforms_splines() {
+        let spline = BSpline::new(ControlVec::new(
+            2,
+            vec![
+                Vector2::new(0f64, 0.),
+                Vector2::new(1., 2.),
+                Vector2::new(2., -1.),
+                Vector2::new(3., 1.),
+            ],
+        ));
+        let rotation = Rotation2::new(0.5);
+        let matrix = Matrix3::new_translation(&Vector2::new(1., -2.)) * rotation.to_homogeneous();
+        let transformed = spline.transformed(&matrix);
+
+        let mut moved = spline.clone();
+        moved.rotate(&rotation);
+        moved.translate(&Vector2::new(1., -2.));
+        for u in [2., 2.5, 3.2, 4.] {
+            let expected = rotation * spline.at(u) + Vector2::new(1., -2.);
+            assert!((transformed.at(u) - expected).norm() < 1e-12);
+            assert!((moved.at(u) - expected).norm() < 1e-12);
+        }
+
+        let mut mirrored = spline.clone();
+        mirrored.mirror(&Vector2::new(0., 2.));
+        mirrored.scale(&Vector2::new(2., 1.));
+        let p = spline.at(3.);
+        assert!((mirrored.at(3.) - Vector2::new(2. * p.x, -p.y)).norm() < 1e-12);
+    }
+
+    #[test]
+    fn it_transforms_nurbs_projectively() {
+        let circle = BSpline::circle();
+        // a perspective division by 1 + x
+        let matrix = Matrix3::new(1., 0., 0., 0., 1., 0., 1., 0., 1.);
+        let transformed = circle.nurbs().transformed(&matrix);
+
+        let (start, end) = circle.range().into_inner();
+        for i in 0..=10 {
+            let u = start + (end - start) * i as f64 / 10.;
+            let p = circle.nurbs().at(u);
+            let expected = p / (1. + p.x);
+            assert!((transformed.nurbs().at(u) - expected).norm() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn it_moves_nurbs_keeping_weights() {
+        let circle = BSpline::circle();
+        let nurbs = circle.nurbs();
+        let rotation = Rotation2::new(0.5);
+        let offset = Vector2::new(1., -2.);
+        let moved = nurbs.rotated(&rotation).nurbs().translated(&offset);
+        let matrix = Matrix3::new_translation(&offset) * rotation.to_homogeneous();
+        let transformed = nurbs.affine_transformed(&matrix);
+        let mirrored = nurbs.mirrored(&Vector2::new(1., 1.));
+        let scaled = nurbs.scaled(&Vector2::new(2., 1.));
+
+        for (p, q) in circle.control_points().iter().zip(moved.control_points()) {
+            assert_eq!(p.z, q.z);
+        }
+        let (start, end) = circle.range().into_inner();
+        for i in 0..=10 {
+            let u = start + (end - start) * i as f64 / 10.;
+            let p = nurbs.at(u);
+            let expected = rotation * p + offset;
+            assert!((moved.nurbs().at(u) - expected).norm() < 1e-12);
+            assert!((transformed.nurbs().at(u) - expected).norm() < 1e-12);
+            assert!((mirrored.nurbs().at(u) - Vector2::new(-p.y, -p.x)).norm() < 1e-12);
+            assert!((scaled.nurbs().at(u) - Vector2::new(2. * p.x, p.y)).norm() < 1e-12);
+        }
+
+        let sphere = BSurface::sphere(1., 0.0..=PI, -1.0..=1.);
+        let moved = sphere.nurbs().translated(&Vector3::new(0., 0., 3.));
+        for uv in [(0., 0.), (0.5, 0.25), (1., 1.)] {
+            let expected = sphere.nurbs().at(uv) + Vector3::new(0., 0., 3.);
+            assert!((moved.nurbs().at(uv) - expected).norm() < 1e-12);
+        }
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic]
+    fn it_rejects_projective_matrices_as_affine() {
+        let matrix = Matrix3::new(1., 0., 0., 0., 1., 0., 1., 0., 1.);
+        BSpline::circle().nurbs().affine_transformed(&matrix);
+    }
+
+    #[test]
+    fn it_transforms_surfaces() {
+        let surface = BSurface::new(ControlGrid::new(
+            1,
+            2,
+            vec![
+                Vector3::new(0f64, 0., 0.),
+                Vector3::new(1., 0., 1.),
+                Vector3::new(0., 1., 0.),
+                Vector3::new(1., 1., 2.),
+            ],
+        ));
+        let nurbs = BSurface::new(ControlGrid::new(
+            1,
+            2,
+            surface
+                .control_points()
+                .iter()
+                .map(|p| p.push(2.))
+                .collect(),
+        ));
+        let matrix = Matrix4::new_nonuniform_scaling(&Vector3::new(2., 3., 4.))
+            .append_translation(&Vector3::new(0., 0., 1.));
+        let transformed = surface.transformed(&matrix);
+        let transformed_nurbs = nurbs.nurbs().transformed(&matrix);
+
+        for uv in [(1., 1.), (1.5, 1.25), (2., 2.)] {
+            let p = surface.at(uv);
+            let expected = Vector3::new(2. * p.x, 3. * p.y, 4. * p.z + 1.);
+            assert!((transformed.at(uv) - expected).norm() < 1e-12);
+        }
+        let expected = Vector3::new(2., 3., 9.);
+        assert!((transformed_nurbs.nurbs().at((2., 2.)) - expected).norm() < 1e-12);
+    }
+}
@@ -44,6 +44,11 @@ impl<T> Scalar for T where
 {
 }
 
+/// Scalars with real valued operations such as square roots, required for geometric queries.
+pub trait Real: Scalar + nalgebra::RealField {}
+
+impl<T> Real for T where T: Scalar + nalgebra::RealField {}
+
 pub type Vector<D, T> =
     nalgebra::Vector<T, D, <DefaultAllocator as nalgebra::allocator::Allocator<T, D>>::Buffer>;
 